@@ -5,16 +5,65 @@ use alloc::string::{String, ToString};
 use alloc::vec::Vec;
 use noli::net::{lookup_host, SocketAddr, TcpStream};
 use saba_core::error::Error;
-use saba_core::http::HttpResponse;
+use saba_core::http::{
+    build_request_with_headers, follow_redirects, read_response, Header, HttpResponse, Transport,
+};
 
-pub struct HttpClient {}
+pub struct HttpClient {
+    max_response_size: Option<usize>, // 受け取るレスポンスの最大バイト数。None なら無制限
+    follow_redirects: bool,           // 3xx のレスポンスで Location をたどるかどうか
+}
 
 impl HttpClient {
     pub fn new() -> Self {
-        Self {}
+        Self {
+            max_response_size: None,
+            follow_redirects: true,
+        }
+    }
+
+    pub fn set_follow_redirects(&mut self, follow_redirects: bool) {
+        self.follow_redirects = follow_redirects;
+    }
+
+    pub fn set_max_response_size(&mut self, max_response_size: Option<usize>) {
+        self.max_response_size = max_response_size;
     }
 
     pub fn get(&self, host: String, port: u16, path: String) -> Result<HttpResponse, Error> {
+        self.get_with_headers(host, port, path, &[])
+    }
+
+    // If-None-Match などのヘッダーを追加して GET する
+    pub fn get_with_headers(
+        &self,
+        host: String,
+        port: u16,
+        path: String,
+        headers: &[Header],
+    ) -> Result<HttpResponse, Error> {
+        if !self.follow_redirects {
+            return self.request("GET", host, port, path, headers);
+        }
+
+        follow_redirects(host, port, path, |host, port, path| {
+            self.request("GET", host.to_string(), port, path.to_string(), headers)
+        })
+    }
+
+    // ボディを受け取らずに、ステータスラインとヘッダーだけを取得する
+    pub fn head(&self, host: String, port: u16, path: String) -> Result<HttpResponse, Error> {
+        self.request("HEAD", host, port, path, &[])
+    }
+
+    fn request(
+        &self,
+        method: &str,
+        host: String,
+        port: u16,
+        path: String,
+        headers: &[Header],
+    ) -> Result<HttpResponse, Error> {
         // 外部 API を呼び出して名前解決（正引き）
         let ips = match lookup_host(&host) {
             Ok(ips) => ips,
@@ -42,17 +91,7 @@ impl HttpClient {
         };
 
         // TCP Stream に送信するデータを構築する
-        let mut request = String::from("GET /");
-        request.push_str(&path);
-        request.push_str(" HTTP/1.1\n");
-
-        // ヘッダーの追加
-        request.push_str("Host: ");
-        request.push_str(&host);
-        request.push_str("\n");
-        request.push_str("Accept: text/html\n");
-        request.push_str("Connection: close\n");
-        request.push_str("\n");
+        let request = build_request_with_headers(method, &host, &path, headers);
 
         // Rust では使う予定のない変数をアンダースコア(_)で始める
         let _bytes_written = match stream.write(request.as_bytes()) {
@@ -60,27 +99,40 @@ impl HttpClient {
             Err(_) => return Err(Error::Network("Failed to write to TCP stream".to_string())),
         };
 
-        let mut received = Vec::new();
-        loop {
-            let mut buf = [0u8; 4096];
-            let bytes_read = match stream.read(&mut buf) {
-                Ok(bytes) => bytes,
-                Err(_) => {
-                    return Err(Error::Network(
-                        "Failed to receive a request from TCP stream".to_string(),
-                    ))
-                }
-            };
-            if bytes_read == 0 {
-                break;
-            }
-            received.extend_from_slice(&buf[..bytes_read]);
-        }
+        // ボディを受け取り終えたら、接続が閉じられるのを待たずに止める
+        // HEAD への応答のように、ボディを持たないレスポンスはヘッダーだけで止める
+        let received = read_response(
+            method,
+            |buf| match stream.read(buf) {
+                Ok(bytes) => Ok(bytes),
+                Err(_) => Err(Error::Network(
+                    "Failed to receive a request from TCP stream".to_string(),
+                )),
+            },
+            self.max_response_size,
+        )?;
 
-        // バイトから文字列型に変換して return
-        match core::str::from_utf8(&received) {
-            Ok(response) => HttpResponse::new(response.to_string()),
-            Err(e) => Err(Error::Network(format!("Invalid received response: {}", e))),
-        }
+        // チャンクを連結してから文字列に変換するので、バイト列のまま渡す
+        HttpResponse::from_bytes(&received)
+    }
+}
+
+impl Transport for HttpClient {
+    fn get(&mut self, host: String, port: u16, path: String) -> Result<HttpResponse, Error> {
+        HttpClient::get(self, host, port, path)
+    }
+
+    fn set_max_response_size(&mut self, max_response_size: Option<usize>) {
+        HttpClient::set_max_response_size(self, max_response_size)
+    }
+
+    fn get_with_headers(
+        &mut self,
+        host: String,
+        port: u16,
+        path: String,
+        headers: Vec<Header>,
+    ) -> Result<HttpResponse, Error> {
+        HttpClient::get_with_headers(self, host, port, path, &headers)
     }
 }
@@ -1,5 +1,17 @@
+use crate::error::Error;
+use crate::http::{mime_guess, Header, HttpResponse, Transport};
+use crate::renderer::css::cssom::{CssParser, StyleSheet};
+use crate::renderer::css::token::CssTokenizer;
+use crate::renderer::dom::node::{ElementKind, Node, NodeKind};
+use crate::renderer::js::runtime::RuntimeValue;
+use crate::renderer::layout::computed_style::DisplayType;
+use crate::renderer::layout::layout_object::LayoutObject;
 use crate::renderer::page::Page;
+use crate::url::Url;
+use alloc::format;
 use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::string::ToString;
 use alloc::vec::Vec;
 use core::cell::RefCell;
 
@@ -7,6 +19,60 @@ use core::cell::RefCell;
 pub struct Browser {
     active_page_index: usize,
     pages: Vec<Rc<RefCell<Page>>>,
+    current_url: Option<String>,
+    back_stack: Vec<String>, // 戻る操作で遷移する URL（最後の要素が直前のページ）
+    forward_stack: Vec<String>, // 進む操作で遷移する URL（最後の要素が直後のページ）
+    user_style: Option<StyleSheet>, // すべてのページに適用するユーザースタイルシート
+    dark_mode: bool,         // ページをダークモードの配色で描画するかどうか
+    max_page_size: Option<usize>, // 取得するリソースの最大バイト数。None なら無制限
+    last_download: Option<(String, Vec<u8>)>, // 最後にダウンロードしたファイル名と内容
+    clock: Clock,            // 読み込みの各段階の時間を測るための現在時刻
+    timings: LoadTimings,    // 最後に読み込んだときの各段階の所要時間
+    cache: Option<Vec<(String, HttpResponse)>>, // 正規化した URL とレスポンス。None ならキャッシュしない
+}
+
+// 現在時刻を返す時計。Browser ごとに持つので、状態を持つクロージャも渡せる
+#[derive(Clone)]
+struct Clock(Rc<dyn Fn() -> u64>);
+
+impl Clock {
+    fn now(&self) -> u64 {
+        (self.0)()
+    }
+}
+
+impl core::fmt::Debug for Clock {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.write_str("Clock")
+    }
+}
+
+// load の各段階の所要時間。単位は設定した時計に従う
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LoadTimings {
+    pub fetch: u64,
+    pub parse_html: u64,
+    pub parse_css: u64,
+    pub cascade: u64,
+    pub script: u64,
+}
+
+// ページの内容に関する簡単な統計情報
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PageStats {
+    pub words: usize,
+    pub links: usize,
+    pub images: usize,
+    pub headings: usize,
+}
+
+// ページ内の画像の参照。width と height は属性がないか数値として解釈できなければ None
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageRef {
+    pub src: String,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub alt: Option<String>,
 }
 
 impl Browser {
@@ -16,6 +82,16 @@ impl Browser {
         let browser = Rc::new(RefCell::new(Self {
             active_page_index: 0,
             pages: Vec::new(),
+            current_url: None,
+            back_stack: Vec::new(),
+            forward_stack: Vec::new(),
+            user_style: None,
+            dark_mode: false,
+            max_page_size: None,
+            last_download: None,
+            clock: Clock(Rc::new(|| 0)),
+            timings: LoadTimings::default(),
+            cache: None,
         }));
 
         page.set_browser(Rc::downgrade(&browser));
@@ -27,4 +103,1201 @@ impl Browser {
     pub fn current_page(&self) -> Rc<RefCell<Page>> {
         self.pages[self.active_page_index].clone()
     }
+
+    pub fn current_url(&self) -> Option<String> {
+        self.current_url.clone()
+    }
+
+    // 新しい URL に遷移したことを履歴に記録する
+    // 戻った後に別の URL へ遷移した場合、進む履歴は破棄される
+    pub fn push_history(&mut self, url: String) {
+        if let Some(current) = self.current_url.take() {
+            self.back_stack.push(current);
+        }
+        self.current_url = Some(url);
+        self.forward_stack.clear();
+    }
+
+    // 1つ前の URL に戻り、その URL を返す。戻る履歴がなければ None
+    pub fn go_back(&mut self) -> Option<String> {
+        let previous = self.back_stack.pop()?;
+        if let Some(current) = self.current_url.take() {
+            self.forward_stack.push(current);
+        }
+        self.current_url = Some(previous.clone());
+        Some(previous)
+    }
+
+    // 戻る前の URL に進み、その URL を返す。進む履歴がなければ None
+    pub fn go_forward(&mut self) -> Option<String> {
+        let next = self.forward_stack.pop()?;
+        if let Some(current) = self.current_url.take() {
+            self.back_stack.push(current);
+        }
+        self.current_url = Some(next.clone());
+        Some(next)
+    }
+
+    // url のリソースを取得して現在のページに表示し、履歴に記録する
+    pub fn load(&mut self, url: &str, transport: &mut dyn Transport) -> Result<(), Error> {
+        let parsed_url = Url::new(url.to_string())
+            .parse()
+            .map_err(Error::UnexpectedInput)?;
+
+        let fetch_start = self.clock.now();
+        let response = self.fetch(&parsed_url, transport)?;
+        let fetch = self.clock.now().saturating_sub(fetch_start);
+
+        self.show_response(url, &parsed_url, response, fetch)
+    }
+
+    // url の HTML を少しずつ受け取り、断片を受け取るたびにそこまでの内容を描画する
+    // 断片ごとに受け取り済みの HTML 全体を解析し直すので、最終的な DOM ツリーは load と同じになる
+    pub fn load_progressive(
+        &mut self,
+        url: &str,
+        transport: &mut dyn Transport,
+    ) -> Result<(), Error> {
+        let parsed_url = Url::new(url.to_string())
+            .parse()
+            .map_err(Error::UnexpectedInput)?;
+        let port = Self::port_number(&parsed_url)?;
+
+        // 表示できないと推測されるリソースは、途中経過を描画しない
+        let render_partial = matches!(
+            Self::mime_for_path(&parsed_url),
+            "text/html" | "application/xhtml+xml"
+        );
+        let page = self.current_page();
+        let mut received = String::new();
+
+        let fetch_start = self.clock.now();
+        transport.set_max_response_size(self.max_page_size);
+        let response = transport.get_chunked(
+            parsed_url.host(),
+            port,
+            Self::request_path(&parsed_url),
+            &mut |chunk| {
+                received.push_str(chunk);
+                if render_partial {
+                    page.borrow_mut()
+                        .receive_partial_body(complete_tags_prefix(&received).to_string());
+                }
+            },
+        )?;
+        self.check_page_size(&response)?;
+        let fetch = self.clock.now().saturating_sub(fetch_start);
+
+        self.show_response(url, &parsed_url, response, fetch)
+    }
+
+    // 取得したレスポンスを、コンテンツタイプに応じて表示するかダウンロードする
+    fn show_response(
+        &mut self,
+        url: &str,
+        parsed_url: &Url,
+        response: HttpResponse,
+        fetch: u64,
+    ) -> Result<(), Error> {
+        match Self::content_type(&response, parsed_url).as_str() {
+            content_type @ ("text/html" | "application/xhtml+xml") => {
+                let page = self.current_page();
+                page.borrow_mut()
+                    .set_xhtml(content_type == "application/xhtml+xml");
+                let clock = self.clock.clone();
+                let timings = page
+                    .borrow_mut()
+                    .receive_response_with_clock(response, &*clock.0);
+                self.timings = LoadTimings { fetch, ..timings };
+                self.push_history(url.to_string());
+                Ok(())
+            }
+            // 表示できないリソースはページを変えずにダウンロードする
+            _ => {
+                self.timings = LoadTimings {
+                    fetch,
+                    ..LoadTimings::default()
+                };
+                let file_name = Self::download_file_name(&response, parsed_url);
+                self.last_download = Some((file_name, response.body_bytes()));
+                Ok(())
+            }
+        }
+    }
+
+    // url のリソースを取得する。キャッシュが有効で保存済みなら、ネットワークを使わずに返す
+    // ETag か Last-Modified を持つレスポンスは、条件付きリクエストで更新されていないか確かめる
+    fn fetch(&mut self, url: &Url, transport: &mut dyn Transport) -> Result<HttpResponse, Error> {
+        let key = url.normalize().url();
+        let cached = self.cache.as_ref().and_then(|cache| {
+            cache
+                .iter()
+                .find(|(k, _)| *k == key)
+                .map(|(_, response)| response.clone())
+        });
+
+        let headers = match &cached {
+            Some(response) => {
+                let headers = Self::conditional_headers(response);
+                if headers.is_empty() {
+                    return Ok(response.clone());
+                }
+                headers
+            }
+            None => Vec::new(),
+        };
+
+        let port = Self::port_number(url)?;
+        transport.set_max_response_size(self.max_page_size);
+        let response =
+            transport.get_with_headers(url.host(), port, Self::request_path(url), headers)?;
+
+        // 304 Not Modified なら保存しておいたレスポンスをそのまま使う
+        if response.status_code() == 304 {
+            if let Some(cached) = cached {
+                return Ok(cached);
+            }
+        }
+        self.check_page_size(&response)?;
+
+        if let Some(cache) = &mut self.cache {
+            if response.status_code() == 200 && !Self::is_no_store(&response) {
+                cache.retain(|(k, _)| *k != key);
+                cache.push((key, response.clone()));
+            }
+        }
+        Ok(response)
+    }
+
+    // 保存したレスポンスの検証子から、条件付きリクエストのヘッダーを作る
+    fn conditional_headers(response: &HttpResponse) -> Vec<Header> {
+        let mut headers = Vec::new();
+        if let Ok(etag) = response.header_value("ETag") {
+            headers.push(Header::new("If-None-Match".to_string(), etag));
+        }
+        if let Ok(last_modified) = response.header_value("Last-Modified") {
+            headers.push(Header::new("If-Modified-Since".to_string(), last_modified));
+        }
+        headers
+    }
+
+    // リクエストで送るパス。クエリがあれば "?" に続けて付ける
+    fn request_path(url: &Url) -> String {
+        let mut path = url.path();
+        if !url.searchpart().is_empty() {
+            path.push('?');
+            path.push_str(&url.searchpart());
+        }
+        path
+    }
+
+    fn port_number(url: &Url) -> Result<u16, Error> {
+        url.port()
+            .parse::<u16>()
+            .map_err(|e| Error::UnexpectedInput(format!("invalid port: {:?}", e)))
+    }
+
+    // Transport が上限に対応していない場合に備えて、受け取った後にもサイズを確認する
+    fn check_page_size(&self, response: &HttpResponse) -> Result<(), Error> {
+        if let Some(max) = self.max_page_size {
+            if response.body().len() > max {
+                return Err(Error::Network(format!(
+                    "response exceeds the maximum size of {} bytes",
+                    max
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    // Cache-Control ヘッダーで保存が禁止されているかどうか
+    fn is_no_store(response: &HttpResponse) -> bool {
+        match response.combined_header_value("Cache-Control") {
+            Ok(value) => value
+                .split(',')
+                .any(|directive| directive.trim().eq_ignore_ascii_case("no-store")),
+            Err(_) => false,
+        }
+    }
+
+    // 取得したレスポンスをメモリに保存して再利用するかどうかを設定する
+    // 無効にすると、保存していたレスポンスは破棄される
+    pub fn set_cache(&mut self, enabled: bool) {
+        if !enabled {
+            self.cache = None;
+        } else if self.cache.is_none() {
+            self.cache = Some(Vec::new());
+        }
+    }
+
+    // 読み込みの時間を測る時計を設定する。no_std ではプラットフォーム側から与える
+    pub fn set_clock<F: Fn() -> u64 + 'static>(&mut self, now: F) {
+        self.clock = Clock(Rc::new(now));
+    }
+
+    pub fn timings(&self) -> LoadTimings {
+        self.timings
+    }
+
+    pub fn last_download(&self) -> Option<(String, Vec<u8>)> {
+        self.last_download.clone()
+    }
+
+    // Content-Disposition ヘッダーの filename パラメータ。なければ URL のパスの最後の部分
+    // サーバーが指定した名前にディレクトリが含まれていても、最後の部分だけを使う
+    fn download_file_name(response: &HttpResponse, url: &Url) -> String {
+        if let Ok(disposition) = response.header_value("Content-Disposition") {
+            for param in disposition.split(';') {
+                if let Some((name, value)) = param.split_once('=') {
+                    if name.trim().eq_ignore_ascii_case("filename") {
+                        if let Some(name) = Self::base_name(value.trim().trim_matches('"')) {
+                            return name;
+                        }
+                    }
+                }
+            }
+        }
+
+        Self::base_name(&url.path()).unwrap_or_else(|| "download".to_string())
+    }
+
+    // パスの最後の部分。空や "." や ".." はファイル名として使えないので None
+    fn base_name(path: &str) -> Option<String> {
+        match path.rsplit(['/', '\\']).next() {
+            Some(name) if !name.is_empty() && name != "." && name != ".." => Some(name.to_string()),
+            _ => None,
+        }
+    }
+
+    // ページ、スタイルシート、スクリプトなど、取得するすべてのリソースのサイズの上限を設定する
+    pub fn set_max_page_size(&mut self, bytes: usize) {
+        self.max_page_size = Some(bytes);
+    }
+
+    pub fn max_page_size(&self) -> Option<usize> {
+        self.max_page_size
+    }
+
+    // Content-Type ヘッダーのメディアタイプ。ヘッダーがなければ URL のパスから推測する
+    fn content_type(response: &HttpResponse, url: &Url) -> String {
+        match response.header_value("Content-Type") {
+            Ok(value) => value
+                .split(';')
+                .next()
+                .unwrap_or("")
+                .trim()
+                .to_ascii_lowercase(),
+            Err(_) => Self::mime_for_path(url).to_string(),
+        }
+    }
+
+    // パスの拡張子からコンテンツタイプを推測する
+    // 拡張子がない場合は、ディレクトリのインデックスとして HTML を返すとみなす
+    pub fn mime_for_path(url: &Url) -> &'static str {
+        match url.extension() {
+            Some(ext) => mime_guess(&ext),
+            None => "text/html",
+        }
+    }
+
+    // 作成者のスタイルよりも優先されるユーザースタイルシートを適用する
+    pub fn apply_user_stylesheet(&mut self, css: &str) {
+        let css_tokenizer = CssTokenizer::new(css.to_string());
+        let style = CssParser::new(css_tokenizer).parse_stylesheet();
+
+        for page in &self.pages {
+            page.borrow_mut().set_user_style(style.clone());
+        }
+        self.user_style = Some(style);
+    }
+
+    // ダークモードを切り替えて、切り替えた後に有効かどうかを返す
+    // 背景を暗く、文字を明るくし、作成者が指定した色は明暗を反転して描画する
+    // ユーザースタイルシートを適用した後の色を置き換えるので、ユーザースタイルシートはそのまま残る
+    pub fn toggle_dark_mode(&mut self) -> bool {
+        self.dark_mode = !self.dark_mode;
+        for page in &self.pages {
+            page.borrow_mut().set_dark_mode(self.dark_mode);
+        }
+        self.dark_mode
+    }
+
+    pub fn is_dark_mode(&self) -> bool {
+        self.dark_mode
+    }
+
+    pub fn user_style(&self) -> Option<StyleSheet> {
+        self.user_style.clone()
+    }
+
+    // 表示されるテキストに含まれる単語数
+    pub fn word_count(&self) -> usize {
+        self.stats().words
+    }
+
+    // ページ内のすべての img 要素を文書順に返す
+    pub fn images(&self) -> Vec<ImageRef> {
+        let mut images = Vec::new();
+        let document = self.current_page().borrow().document();
+        collect_images(&document, &mut images);
+        images
+    }
+
+    // コンソールから入力したスクリプトを現在のページで評価する
+    pub fn evaluate_script(&mut self, src: &str) -> Result<RuntimeValue, Error> {
+        self.current_page().borrow_mut().evaluate_script(src)
+    }
+
+    // レイアウトツリーを、各ボックスの display と (x, y, w, h) とともにインデントして出力する
+    pub fn dump_layout(&self) -> String {
+        let mut out = String::new();
+        let root = self.current_page().borrow().layout_root();
+        dump_layout_object(&root, 0, &mut out);
+        out
+    }
+
+    pub fn stats(&self) -> PageStats {
+        let mut stats = PageStats::default();
+        let document = self.current_page().borrow().document();
+        collect_stats(&document, &mut stats);
+        stats
+    }
+}
+
+// 受け取り途中の HTML のうち、閉じていないタグの手前までを返す
+// 途中までのタグ名を別の要素として解析しないようにする
+fn complete_tags_prefix(html: &str) -> &str {
+    match html.rfind('<') {
+        Some(index) if !html[index..].contains('>') => &html[..index],
+        _ => html,
+    }
+}
+
+fn collect_stats(node: &Option<Rc<RefCell<Node>>>, stats: &mut PageStats) {
+    let n = match node {
+        Some(n) => n,
+        None => return,
+    };
+
+    match n.borrow().kind() {
+        NodeKind::Text(text) => stats.words += text.split_whitespace().count(),
+        NodeKind::Element(e) => match e.kind() {
+            // スタイルやスクリプトの中身は表示されないので数えない
+            ElementKind::Head | ElementKind::Style | ElementKind::Script => {
+                collect_stats(&n.borrow().next_sibling(), stats);
+                return;
+            }
+            ElementKind::A => stats.links += 1,
+            ElementKind::Img => stats.images += 1,
+            _ if e.is_heading_element() => stats.headings += 1,
+            _ => {}
+        },
+        NodeKind::Document => {}
+    }
+
+    collect_stats(&n.borrow().first_child(), stats);
+    collect_stats(&n.borrow().next_sibling(), stats);
+}
+
+fn dump_layout_object(object: &Option<Rc<RefCell<LayoutObject>>>, depth: usize, out: &mut String) {
+    let o = match object {
+        Some(o) => o,
+        None => return,
+    };
+
+    let label = match o.borrow().node_kind() {
+        NodeKind::Element(e) => e.tag_name(),
+        NodeKind::Text(text) => format!("{:?}", text),
+        NodeKind::Document => "#document".to_string(),
+    };
+    let display = match o.borrow().style().display() {
+        DisplayType::Block => "block",
+        DisplayType::Inline => "inline",
+        DisplayType::DisplayNone => "none",
+    };
+    let point = o.borrow().point();
+    let size = o.borrow().size();
+    out.push_str(&format!(
+        "{}{} {} ({}, {}, {}, {})\n",
+        "  ".repeat(depth),
+        label,
+        display,
+        point.x(),
+        point.y(),
+        size.width(),
+        size.height()
+    ));
+
+    dump_layout_object(&o.borrow().first_child(), depth + 1, out);
+    dump_layout_object(&o.borrow().next_sibling(), depth, out);
+}
+
+fn collect_images(node: &Option<Rc<RefCell<Node>>>, images: &mut Vec<ImageRef>) {
+    let n = match node {
+        Some(n) => n,
+        None => return,
+    };
+
+    if let Some(e) = n.borrow().get_element() {
+        if e.kind() == ElementKind::Img {
+            images.push(ImageRef {
+                src: e.get_attribute("src").unwrap_or_default(),
+                width: e.get_attribute("width").and_then(|w| w.trim().parse().ok()),
+                height: e
+                    .get_attribute("height")
+                    .and_then(|h| h.trim().parse().ok()),
+                alt: e.get_attribute("alt"),
+            });
+        }
+    }
+
+    collect_images(&n.borrow().first_child(), images);
+    collect_images(&n.borrow().next_sibling(), images);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display_item::DisplayItem;
+    use crate::renderer::dom::node::NodeIterator;
+    use crate::renderer::layout::computed_style::Color;
+    use alloc::vec;
+    use core::cell::Cell;
+
+    // 決まったレスポンスを返し、受け取ったリクエストを記録するテスト用の Transport
+    struct StubTransport {
+        response: Vec<u8>,
+        requests: Vec<(String, u16, String)>,
+        max_response_size: Option<usize>,
+    }
+
+    impl StubTransport {
+        fn new(response: &str) -> Self {
+            Self::from_bytes(response.as_bytes())
+        }
+
+        fn from_bytes(response: &[u8]) -> Self {
+            Self {
+                response: response.to_vec(),
+                requests: Vec::new(),
+                max_response_size: None,
+            }
+        }
+    }
+
+    impl Transport for StubTransport {
+        fn get(&mut self, host: String, port: u16, path: String) -> Result<HttpResponse, Error> {
+            self.requests.push((host, port, path));
+            HttpResponse::from_bytes(&self.response)
+        }
+
+        fn set_max_response_size(&mut self, max_response_size: Option<usize>) {
+            self.max_response_size = max_response_size;
+        }
+    }
+
+    #[test]
+    fn test_stats() {
+        let browser = Browser::new();
+        let raw = "HTTP/1.1 200 OK\n\n<html><head><style>p { color: red; }</style></head>\
+                   <body><h1>Big title</h1><p>Hello <a href=\"x\">small link</a></p>\
+                   <img src=\"a.png\"><h2>Sub</h2><a>other</a><img src=\"b.png\"></body></html>"
+            .to_string();
+        let response = HttpResponse::new(raw).expect("failed to parse response");
+        browser
+            .borrow()
+            .current_page()
+            .borrow_mut()
+            .receive_response(response);
+
+        let expected = PageStats {
+            words: 7,
+            links: 2,
+            images: 2,
+            headings: 2,
+        };
+        assert_eq!(expected, browser.borrow().stats());
+        assert_eq!(7, browser.borrow().word_count());
+    }
+
+    #[test]
+    fn test_stats_without_page() {
+        let browser = Browser::new();
+        assert_eq!(PageStats::default(), browser.borrow().stats());
+        assert_eq!(0, browser.borrow().word_count());
+    }
+
+    #[test]
+    fn test_back_and_forward() {
+        let browser = Browser::new();
+        let mut b = browser.borrow_mut();
+        b.push_history("http://a.com".to_string());
+        b.push_history("http://b.com".to_string());
+        b.push_history("http://c.com".to_string());
+
+        assert_eq!(Some("http://b.com".to_string()), b.go_back());
+        assert_eq!(Some("http://a.com".to_string()), b.go_back());
+        assert_eq!(None, b.go_back());
+        assert_eq!(Some("http://a.com".to_string()), b.current_url());
+
+        assert_eq!(Some("http://b.com".to_string()), b.go_forward());
+        assert_eq!(Some("http://c.com".to_string()), b.go_forward());
+        assert_eq!(None, b.go_forward());
+        assert_eq!(Some("http://c.com".to_string()), b.current_url());
+    }
+
+    #[test]
+    fn test_navigate_after_back_truncates_forward() {
+        let browser = Browser::new();
+        let mut b = browser.borrow_mut();
+        b.push_history("http://a.com".to_string());
+        b.push_history("http://b.com".to_string());
+        b.push_history("http://c.com".to_string());
+
+        assert_eq!(Some("http://b.com".to_string()), b.go_back());
+        b.push_history("http://d.com".to_string());
+
+        assert_eq!(None, b.go_forward());
+        assert_eq!(Some("http://d.com".to_string()), b.current_url());
+        assert_eq!(Some("http://b.com".to_string()), b.go_back());
+        assert_eq!(Some("http://a.com".to_string()), b.go_back());
+    }
+
+    #[test]
+    fn test_user_stylesheet_overrides_author() {
+        let browser = Browser::new();
+        let raw = "HTTP/1.1 200 OK\n\n<html><head><style>p { color: lightgray; }</style></head>\
+                   <body><p>text</p></body></html>"
+            .to_string();
+        let response = HttpResponse::new(raw).expect("failed to parse response");
+        let page = browser.borrow().current_page();
+        page.borrow_mut().receive_response(response);
+
+        browser
+            .borrow_mut()
+            .apply_user_stylesheet("p { color: black; }");
+
+        let text_color = page
+            .borrow()
+            .display_items()
+            .iter()
+            .find_map(|item| match item {
+                DisplayItem::Text { style, .. } => Some(style.color()),
+                _ => None,
+            });
+        assert_eq!(
+            Some(Color::from_name("black").expect("failed to create a color")),
+            text_color
+        );
+    }
+
+    #[test]
+    fn test_user_stylesheet_beats_more_specific_author_rule() {
+        let browser = Browser::new();
+        let raw =
+            "HTTP/1.1 200 OK\n\n<html><head><style>.note { color: lightgray; }</style></head>\
+                   <body><p class=\"note\">text</p></body></html>"
+                .to_string();
+        let response = HttpResponse::new(raw).expect("failed to parse response");
+        let page = browser.borrow().current_page();
+        page.borrow_mut().receive_response(response);
+
+        browser
+            .borrow_mut()
+            .apply_user_stylesheet("p { color: black; }");
+
+        let text_color = page
+            .borrow()
+            .display_items()
+            .iter()
+            .find_map(|item| match item {
+                DisplayItem::Text { style, .. } => Some(style.color()),
+                _ => None,
+            });
+        assert_eq!(
+            Some(Color::from_name("black").expect("failed to create a color")),
+            text_color
+        );
+    }
+
+    // ページの背景の色と、最初の文字の色の組
+    fn page_colors(page: &Rc<RefCell<Page>>) -> (Option<Color>, Option<Color>) {
+        let items = page.borrow().display_items();
+        let background = items.iter().find_map(|item| match item {
+            DisplayItem::Rect { style, .. } => Some(style.background_color()),
+            _ => None,
+        });
+        let text = items.iter().find_map(|item| match item {
+            DisplayItem::Text { style, .. } => Some(style.color()),
+            _ => None,
+        });
+        (background, text)
+    }
+
+    #[test]
+    fn test_toggle_dark_mode() {
+        let browser = Browser::new();
+        let raw =
+            "HTTP/1.1 200 OK\n\n<html><head></head><body><p>text</p></body></html>".to_string();
+        let response = HttpResponse::new(raw).expect("failed to parse response");
+        let page = browser.borrow().current_page();
+        page.borrow_mut().receive_response(response);
+
+        let original = page_colors(&page);
+        assert_eq!((Some(Color::white()), Some(Color::black())), original);
+
+        assert!(browser.borrow_mut().toggle_dark_mode());
+        assert_eq!(
+            (
+                Some(Color::from_hex("#202020").expect("failed to create a color")),
+                Some(Color::from_hex("#e0e0e0").expect("failed to create a color"))
+            ),
+            page_colors(&page)
+        );
+
+        assert!(!browser.borrow_mut().toggle_dark_mode());
+        assert_eq!(original, page_colors(&page));
+    }
+
+    #[test]
+    fn test_dark_mode_inverts_author_colors() {
+        let browser = Browser::new();
+        let raw = "HTTP/1.1 200 OK\n\n<html><head><style>\
+                   body { background-color: #ffff80; }\
+                   #x { color: red !important; }\
+                   </style></head><body><p id=\"x\">text</p></body></html>"
+            .to_string();
+        let response = HttpResponse::new(raw).expect("failed to parse response");
+        let page = browser.borrow().current_page();
+        page.borrow_mut().receive_response(response);
+
+        assert!(browser.borrow_mut().toggle_dark_mode());
+        // 子要素が継承した色も、一度だけ反転する
+        assert_eq!(
+            (
+                Some(Color::from_hex("#00007f").expect("failed to create a color")),
+                Some(Color::from_name("aqua").expect("failed to create a color"))
+            ),
+            page_colors(&page)
+        );
+        assert!(page.borrow().display_items().iter().all(|item| match item {
+            DisplayItem::Rect { style, .. } => style.background_color().code_u32() == 0x00007f,
+            DisplayItem::Text { .. } => true,
+        }));
+    }
+
+    #[test]
+    fn test_load() {
+        let browser = Browser::new();
+        let mut transport =
+            StubTransport::new("HTTP/1.1 200 OK\nContent-Type: text/html; charset=utf-8\n\n<html><body><p>hello</p></body></html>");
+
+        assert_eq!(
+            Ok(()),
+            browser
+                .borrow_mut()
+                .load("http://example.com:8000/index.txt", &mut transport)
+        );
+        assert_eq!(
+            vec![("example.com".to_string(), 8000, "index.txt".to_string())],
+            transport.requests
+        );
+        assert_eq!(1, browser.borrow().word_count());
+        assert_eq!(
+            Some("http://example.com:8000/index.txt".to_string()),
+            browser.borrow().current_url()
+        );
+    }
+
+    #[test]
+    fn test_load_guesses_content_type_from_path() {
+        let browser = Browser::new();
+        let mut transport =
+            StubTransport::new("HTTP/1.1 200 OK\n\n<html><body><p>hello</p></body></html>");
+
+        assert_eq!(
+            Ok(()),
+            browser
+                .borrow_mut()
+                .load("http://example.com/index.html", &mut transport)
+        );
+        // HTML 以外のリソースは表示せずにダウンロードする
+        assert_eq!(
+            Ok(()),
+            browser
+                .borrow_mut()
+                .load("http://example.com/style.css", &mut transport)
+        );
+        assert_eq!(
+            Some("style.css".to_string()),
+            browser.borrow().last_download().map(|(name, _)| name)
+        );
+        assert_eq!(
+            Some("http://example.com/index.html".to_string()),
+            browser.borrow().current_url()
+        );
+    }
+
+    #[test]
+    fn test_load_xhtml() {
+        let browser = Browser::new();
+        let mut transport = StubTransport::new("HTTP/1.1 200 OK\nContent-Type: application/xhtml+xml\n\n<html><head></head><body><div/><p>x</p></body></html>");
+
+        assert_eq!(
+            Ok(()),
+            browser
+                .borrow_mut()
+                .load("http://example.com/index.xhtml", &mut transport)
+        );
+        let document = browser
+            .borrow()
+            .current_page()
+            .borrow()
+            .document()
+            .expect("failed to get a document");
+        assert_eq!(
+            "<html><head></head><body><div></div><p>x</p></body></html>".to_string(),
+            document.borrow().outer_html()
+        );
+    }
+
+    #[test]
+    fn test_download() {
+        let browser = Browser::new();
+        let mut transport = StubTransport::new(
+            "HTTP/1.1 200 OK\nContent-Type: application/octet-stream\n\nbinary data",
+        );
+
+        assert_eq!(
+            Ok(()),
+            browser
+                .borrow_mut()
+                .load("http://example.com/files/data.bin", &mut transport)
+        );
+        assert_eq!(
+            Some(("data.bin".to_string(), b"binary data".to_vec())),
+            browser.borrow().last_download()
+        );
+        assert_eq!(None, browser.borrow().current_url());
+
+        let mut transport = StubTransport::new(
+            "HTTP/1.1 200 OK\nContent-Type: application/octet-stream\nContent-Disposition: attachment; filename=\"report.pdf\"\n\n%PDF",
+        );
+        assert_eq!(
+            Ok(()),
+            browser
+                .borrow_mut()
+                .load("http://example.com/download", &mut transport)
+        );
+        assert_eq!(
+            Some(("report.pdf".to_string(), b"%PDF".to_vec())),
+            browser.borrow().last_download()
+        );
+    }
+
+    #[test]
+    fn test_download_file_name_is_sanitized() {
+        let file_name = |disposition: &str, url: &str| {
+            let response = HttpResponse::new(format!(
+                "HTTP/1.1 200 OK\nContent-Disposition: attachment; filename=\"{}\"\n\n",
+                disposition
+            ))
+            .expect("failed to parse response");
+            let url = Url::new(url.to_string())
+                .parse()
+                .expect("failed to parse url");
+            Browser::download_file_name(&response, &url)
+        };
+        assert_eq!("x", file_name("../../etc/x", "http://example.com/a"));
+        assert_eq!("passwd", file_name("/etc/passwd", "http://example.com/a"));
+        assert_eq!(
+            "evil.exe",
+            file_name("..\\..\\evil.exe", "http://example.com/a")
+        );
+        assert_eq!("a.bin", file_name("..", "http://example.com/a.bin"));
+        assert_eq!("a.bin", file_name("dir/", "http://example.com/a.bin"));
+        assert_eq!("download", file_name(".", "http://example.com/.."));
+        assert_eq!("download", file_name("", "http://example.com/"));
+    }
+
+    #[test]
+    fn test_download_binary_body() {
+        let browser = Browser::new();
+        let mut response = b"HTTP/1.1 200 OK\nContent-Type: application/octet-stream\n\n".to_vec();
+        response.extend_from_slice(b"\x89PNG\xff\x00");
+        let mut transport = StubTransport::from_bytes(&response);
+        assert_eq!(
+            Ok(()),
+            browser
+                .borrow_mut()
+                .load("http://example.com/image.png", &mut transport)
+        );
+        assert_eq!(
+            Some(("image.png".to_string(), b"\x89PNG\xff\x00".to_vec())),
+            browser.borrow().last_download()
+        );
+    }
+
+    #[test]
+    fn test_mime_for_path() {
+        let url = |s: &str| {
+            Url::new(s.to_string())
+                .parse()
+                .expect("failed to parse url")
+        };
+        assert_eq!(
+            "text/html",
+            Browser::mime_for_path(&url("http://a.com/index.html"))
+        );
+        assert_eq!(
+            "text/css",
+            Browser::mime_for_path(&url("http://a.com/style.css"))
+        );
+        assert_eq!(
+            "text/javascript",
+            Browser::mime_for_path(&url("http://a.com/app.js"))
+        );
+        assert_eq!(
+            "text/plain",
+            Browser::mime_for_path(&url("http://a.com/README.txt"))
+        );
+        assert_eq!(
+            "text/html",
+            Browser::mime_for_path(&url("http://a.com/docs"))
+        );
+    }
+
+    #[test]
+    fn test_max_page_size() {
+        let browser = Browser::new();
+        browser.borrow_mut().set_max_page_size(16);
+        let mut transport =
+            StubTransport::new("HTTP/1.1 200 OK\n\n<html><body><p>too large</p></body></html>");
+
+        match browser
+            .borrow_mut()
+            .load("http://example.com/index.html", &mut transport)
+        {
+            Err(Error::Network(_)) => {}
+            result => panic!("expected a network error: {:?}", result),
+        }
+        assert_eq!(Some(16), transport.max_response_size);
+        assert_eq!(None, browser.borrow().current_url());
+    }
+
+    #[test]
+    fn test_images() {
+        let browser = Browser::new();
+        let mut transport = StubTransport::new("HTTP/1.1 200 OK\n\n<html><body><p><img src=\"cat.png\" width=\"100\" height=\"50\" alt=\"a cat\"></p><img src=\"dog.png\"></body></html>");
+        assert_eq!(
+            Ok(()),
+            browser
+                .borrow_mut()
+                .load("http://example.com/index.html", &mut transport)
+        );
+
+        assert_eq!(
+            vec![
+                ImageRef {
+                    src: "cat.png".to_string(),
+                    width: Some(100),
+                    height: Some(50),
+                    alt: Some("a cat".to_string()),
+                },
+                ImageRef {
+                    src: "dog.png".to_string(),
+                    width: None,
+                    height: None,
+                    alt: None,
+                },
+            ],
+            browser.borrow().images()
+        );
+    }
+
+    // 呼ばれるたびに決まった時刻を順に返すテスト用の時計
+    // 呼び出し回数は時計ごとに数えるので、ほかのテストの影響を受けない
+    fn fake_clock() -> impl Fn() -> u64 {
+        let ticks: [u64; 8] = [0, 30, 30, 35, 35, 37, 37, 47];
+        let calls = Cell::new(0);
+        move || {
+            let i = calls.get();
+            calls.set(i + 1);
+            ticks[i.min(ticks.len() - 1)]
+        }
+    }
+
+    #[test]
+    fn test_timings() {
+        let browser = Browser::new();
+        browser.borrow_mut().set_clock(fake_clock());
+        let mut transport = StubTransport::new(
+            "HTTP/1.1 200 OK\n\n<html><head><style>p { color: red; }</style></head><body><p>hi</p></body></html>",
+        );
+        assert_eq!(
+            Ok(()),
+            browser
+                .borrow_mut()
+                .load("http://example.com/index.html", &mut transport)
+        );
+
+        assert_eq!(
+            LoadTimings {
+                fetch: 30,
+                parse_html: 5,
+                parse_css: 2,
+                cascade: 10,
+                script: 0,
+            },
+            browser.borrow().timings()
+        );
+    }
+
+    #[test]
+    fn test_evaluate_script() {
+        let browser = Browser::new();
+        assert_eq!(
+            Ok(RuntimeValue::Undefined),
+            browser.borrow_mut().evaluate_script("var x = 10;")
+        );
+        // 前の呼び出しで定義した変数が残っている
+        assert_eq!(
+            Ok(RuntimeValue::Number(20.0)),
+            browser.borrow_mut().evaluate_script("x * 2")
+        );
+    }
+
+    #[test]
+    fn test_dump_layout() {
+        let browser = Browser::new();
+        let mut transport = StubTransport::new(
+            "HTTP/1.1 200 OK\n\n<html><body><p>first</p><p>second</p></body></html>",
+        );
+        assert_eq!(
+            Ok(()),
+            browser
+                .borrow_mut()
+                .load("http://example.com/index.html", &mut transport)
+        );
+
+        // 2つの段落は縦に積み重なる
+        let expected = concat!(
+            "body block (0, 0, 590, 40)\n",
+            "  p block (0, 0, 590, 20)\n",
+            "    \"first\" inline (0, 0, 40, 20)\n",
+            "  p block (0, 20, 590, 20)\n",
+            "    \"second\" inline (0, 20, 48, 20)\n",
+        );
+        assert_eq!(expected, browser.borrow().dump_layout());
+    }
+
+    #[test]
+    fn test_cache() {
+        let browser = Browser::new();
+        browser.borrow_mut().set_cache(true);
+        let mut transport =
+            StubTransport::new("HTTP/1.1 200 OK\n\n<html><body><p>hello</p></body></html>");
+
+        for url in [
+            "http://example.com/a/index.html",
+            "http://Example.com/a/./index.html",
+        ] {
+            assert_eq!(Ok(()), browser.borrow_mut().load(url, &mut transport));
+            assert_eq!(1, browser.borrow().word_count());
+        }
+        assert_eq!(1, transport.requests.len());
+    }
+
+    #[test]
+    fn test_cache_no_store() {
+        let browser = Browser::new();
+        browser.borrow_mut().set_cache(true);
+        let mut transport = StubTransport::new(
+            "HTTP/1.1 200 OK\nCache-Control: private, no-store\n\n<html><body><p>hello</p></body></html>",
+        );
+
+        for _ in 0..2 {
+            assert_eq!(
+                Ok(()),
+                browser
+                    .borrow_mut()
+                    .load("http://example.com/index.html", &mut transport)
+            );
+        }
+        assert_eq!(2, transport.requests.len());
+    }
+
+    // 用意したレスポンスを順に返し、リクエストごとに追加されたヘッダーを記録する
+    struct RevalidatingTransport {
+        responses: Vec<String>,
+        sent_headers: Vec<Vec<(String, String)>>,
+    }
+
+    impl Transport for RevalidatingTransport {
+        fn get(&mut self, host: String, port: u16, path: String) -> Result<HttpResponse, Error> {
+            self.get_with_headers(host, port, path, Vec::new())
+        }
+
+        fn get_with_headers(
+            &mut self,
+            _host: String,
+            _port: u16,
+            _path: String,
+            headers: Vec<Header>,
+        ) -> Result<HttpResponse, Error> {
+            self.sent_headers
+                .push(headers.iter().map(|h| (h.name(), h.value())).collect());
+            HttpResponse::new(self.responses.remove(0))
+        }
+    }
+
+    #[test]
+    fn test_cache_revalidation() {
+        let browser = Browser::new();
+        browser.borrow_mut().set_cache(true);
+        let mut transport = RevalidatingTransport {
+            responses: [
+                "HTTP/1.1 200 OK\nETag: \"v1\"\nLast-Modified: Tue, 13 Oct 2026 00:00:00 GMT\n\n<html><body><p>hello</p></body></html>".to_string(),
+                "HTTP/1.1 304 Not Modified\n\n".to_string(),
+            ]
+            .to_vec(),
+            sent_headers: Vec::new(),
+        };
+
+        for _ in 0..2 {
+            assert_eq!(
+                Ok(()),
+                browser
+                    .borrow_mut()
+                    .load("http://example.com/index.html", &mut transport)
+            );
+            // 2回目は 304 なので、保存しておいたボディが表示される
+            assert_eq!(1, browser.borrow().word_count());
+        }
+
+        assert_eq!(2, transport.sent_headers.len());
+        assert!(transport.sent_headers[0].is_empty());
+        assert_eq!(
+            [
+                ("If-None-Match".to_string(), "\"v1\"".to_string()),
+                (
+                    "If-Modified-Since".to_string(),
+                    "Tue, 13 Oct 2026 00:00:00 GMT".to_string()
+                ),
+            ]
+            .to_vec(),
+            transport.sent_headers[1]
+        );
+    }
+
+    // ボディを決まった大きさの断片に分けて渡し、断片ごとのページの DOM ノード数を記録する
+    struct ChunkedTransport {
+        response: String,
+        chunk_size: usize,
+        page: Rc<RefCell<Page>>,
+        node_counts: Vec<usize>,
+    }
+
+    impl Transport for ChunkedTransport {
+        fn get(&mut self, _host: String, _port: u16, _path: String) -> Result<HttpResponse, Error> {
+            HttpResponse::new(self.response.clone())
+        }
+
+        fn get_chunked(
+            &mut self,
+            host: String,
+            port: u16,
+            path: String,
+            on_chunk: &mut dyn FnMut(&str),
+        ) -> Result<HttpResponse, Error> {
+            let response = self.get(host, port, path)?;
+            let body: Vec<char> = response.body().chars().collect();
+            for chunk in body.chunks(self.chunk_size) {
+                on_chunk(&chunk.iter().collect::<String>());
+                let count = match self.page.borrow().document() {
+                    Some(document) => NodeIterator::new(document).count(),
+                    None => 0,
+                };
+                self.node_counts.push(count);
+            }
+            Ok(response)
+        }
+    }
+
+    #[test]
+    fn test_load_sends_query() {
+        let browser = Browser::new();
+        let mut transport = StubTransport::new("HTTP/1.1 200 OK\n\n<p>hi</p>");
+        assert_eq!(
+            Ok(()),
+            browser
+                .borrow_mut()
+                .load("http://example.com/search?q=saba&page=2", &mut transport)
+        );
+        assert_eq!(
+            Ok(()),
+            browser
+                .borrow_mut()
+                .load_progressive("http://example.com/search?q=wasabi", &mut transport)
+        );
+        assert_eq!(
+            vec![
+                (
+                    "example.com".to_string(),
+                    80,
+                    "search?q=saba&page=2".to_string()
+                ),
+                ("example.com".to_string(), 80, "search?q=wasabi".to_string()),
+            ],
+            transport.requests
+        );
+    }
+
+    #[test]
+    fn test_load_progressive() {
+        let raw = "HTTP/1.1 200 OK\n\n<html><head></head><body><p class=\"a\">one</p><p>two</p><p>three</p></body></html>";
+
+        let expected = Browser::new();
+        let mut transport = StubTransport::new(raw);
+        assert_eq!(
+            Ok(()),
+            expected
+                .borrow_mut()
+                .load("http://example.com/index.html", &mut transport)
+        );
+
+        let browser = Browser::new();
+        let mut transport = ChunkedTransport {
+            response: raw.to_string(),
+            chunk_size: 7,
+            page: browser.borrow().current_page(),
+            node_counts: Vec::new(),
+        };
+        assert_eq!(
+            Ok(()),
+            browser
+                .borrow_mut()
+                .load_progressive("http://example.com/index.html", &mut transport)
+        );
+
+        // 断片を受け取るたびに DOM ツリーが育っていく
+        let counts = &transport.node_counts;
+        assert!(counts.len() > 1);
+        assert!(counts.windows(2).all(|w| w[0] <= w[1]));
+        assert!(counts[0] < counts[counts.len() - 1]);
+
+        let outer_html = |b: &Rc<RefCell<Browser>>| {
+            b.borrow()
+                .current_page()
+                .borrow()
+                .document()
+                .and_then(|d| d.borrow().first_child())
+                .map(|html| html.borrow().outer_html())
+        };
+        assert_eq!(outer_html(&expected), outer_html(&browser));
+        assert_eq!(
+            Some("http://example.com/index.html".to_string()),
+            browser.borrow().current_url()
+        );
+    }
 }
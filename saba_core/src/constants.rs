@@ -26,3 +26,6 @@ pub static CONTENT_AREA_HEIGHT: i64 =
 pub static CHAR_WIDTH: i64 = 8;
 pub static CHAR_HEIGHT: i64 = 16;
 pub static CHAR_WITH_PADDING: i64 = CHAR_HEIGHT + 4;
+
+// white-space: pre のテキストでタブを展開する桁数の初期値
+pub static TAB_SIZE: usize = 8;
@@ -1,8 +1,312 @@
 use crate::error::Error;
+use crate::url::Url;
 use alloc::format;
 use alloc::string::{String, ToString};
 use alloc::vec::Vec;
 
+// HTTP リクエストを送信してレスポンスを受け取る手段
+// 実際のネットワークを使う実装はプラットフォームごとのクレートが提供する
+pub trait Transport {
+    fn get(&mut self, host: String, port: u16, path: String) -> Result<HttpResponse, Error>;
+
+    // 受け取るレスポンスの最大バイト数を設定する。None なら無制限
+    // 上限に対応しない実装もあるので、呼び出し側でも受け取った後に確認する
+    fn set_max_response_size(&mut self, _max_response_size: Option<usize>) {}
+
+    // headers をリクエストヘッダーに追加して GET する。条件付きリクエストに使う
+    // 既定の実装はヘッダーを送らずに get するので、常に通常のレスポンスが返る
+    fn get_with_headers(
+        &mut self,
+        host: String,
+        port: u16,
+        path: String,
+        _headers: Vec<Header>,
+    ) -> Result<HttpResponse, Error> {
+        self.get(host, port, path)
+    }
+
+    // レスポンスのボディを断片ごとに on_chunk へ渡しながら受け取る
+    // 既定の実装は、get で全体を受け取ってから1つの断片として渡す
+    fn get_chunked(
+        &mut self,
+        host: String,
+        port: u16,
+        path: String,
+        on_chunk: &mut dyn FnMut(&str),
+    ) -> Result<HttpResponse, Error> {
+        let response = self.get(host, port, path)?;
+        on_chunk(&response.body());
+        Ok(response)
+    }
+}
+
+// 拡張子からコンテンツタイプを推測する
+pub fn mime_guess(ext: &str) -> &'static str {
+    match ext {
+        "html" | "htm" => "text/html",
+        "xhtml" => "application/xhtml+xml",
+        "css" => "text/css",
+        "js" => "text/javascript",
+        "txt" => "text/plain",
+        _ => "application/octet-stream",
+    }
+}
+
+// method で指定したメソッドの HTTP リクエストを構築する
+pub fn build_request(method: &str, host: &str, path: &str) -> String {
+    build_request_with_headers(method, host, path, &[])
+}
+
+// 既定のヘッダーの後に headers を追加したリクエストを構築する
+pub fn build_request_with_headers(
+    method: &str,
+    host: &str,
+    path: &str,
+    headers: &[Header],
+) -> String {
+    let mut request = String::from(method);
+    // path の先頭に '/' があってもなくても、リクエストラインの '/' は1つにする
+    request.push_str(" /");
+    request.push_str(path.trim_start_matches('/'));
+    request.push_str(" HTTP/1.1\r\n");
+
+    // ヘッダーの追加。HTTP では各行を CRLF で終える
+    request.push_str("Host: ");
+    request.push_str(host);
+    request.push_str("\r\n");
+    request.push_str("Accept: text/html\r\n");
+    request.push_str("Connection: close\r\n");
+    for header in headers {
+        request.push_str(&format!("{}: {}\r\n", header.name, header.value));
+    }
+    // 空行でヘッダーの終わりを示す
+    request.push_str("\r\n");
+
+    request
+}
+
+// リダイレクトをたどる最大の回数
+pub const MAX_REDIRECTS: usize = 5;
+
+// リダイレクトのレスポンスであれば、Location ヘッダーから次のリクエスト先 (host, port, path) を求める
+// リダイレクトでなければ None を返す。path は host, port, path で送ったリクエストのもの
+pub fn redirect_target(
+    response: &HttpResponse,
+    host: &str,
+    port: u16,
+    path: &str,
+) -> Result<Option<(String, u16, String)>, Error> {
+    if !matches!(response.status_code(), 301 | 302 | 303 | 307 | 308) {
+        return Ok(None);
+    }
+
+    let location = match response.header_value("Location") {
+        Ok(location) => location,
+        Err(_) => {
+            return Err(Error::Network(format!(
+                "redirect ({}) without Location header",
+                response.status_code()
+            )))
+        }
+    };
+
+    // "//host/path" はスキームを省略した URL なので、今のスキーム (http) で補う
+    let location = match location.strip_prefix("//") {
+        Some(rest) => format!("http://{}", rest),
+        None => location,
+    };
+
+    // http 以外のスキームの URL にはたどれない
+    if !location.starts_with("http://") && has_scheme(&location) {
+        return Err(Error::Network(format!(
+            "unsupported redirect scheme: {}",
+            location
+        )));
+    }
+
+    // 絶対 URL
+    if location.starts_with("http://") {
+        let url = match Url::new(location.clone()).parse() {
+            Ok(url) => url,
+            Err(e) => return Err(Error::Network(format!("{}: {}", e, location))),
+        };
+        let port = match url.port().parse::<u16>() {
+            Ok(port) => port,
+            Err(_) => return Err(Error::Network(format!("Invalid port: {}", location))),
+        };
+        let mut path = url.path();
+        if !url.searchpart().is_empty() {
+            path.push('?');
+            path.push_str(&url.searchpart());
+        }
+        return Ok(Some((url.host(), port, path)));
+    }
+
+    // '/' で始まる場合はホストのルートから、それ以外は今のパスのディレクトリからの相対パス
+    let next_path = match location.strip_prefix('/') {
+        Some(p) => p.to_string(),
+        None => {
+            let path = path.trim_start_matches('/');
+            match path.rsplit_once('/') {
+                Some((dir, _)) => format!("{}/{}", dir, location),
+                None => location,
+            }
+        }
+    };
+    Ok(Some((host.to_string(), port, next_path)))
+}
+
+// "https:" や "mailto:" のように、先頭がスキームかどうか
+fn has_scheme(location: &str) -> bool {
+    let scheme = match location.split_once(':') {
+        Some((scheme, _)) => scheme,
+        None => return false,
+    };
+    let mut chars = scheme.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.')
+        }
+        _ => false,
+    }
+}
+
+// send でリクエストを送り、リダイレクトのレスポンスであれば MAX_REDIRECTS 回までたどる
+pub fn follow_redirects<F>(
+    host: String,
+    port: u16,
+    path: String,
+    mut send: F,
+) -> Result<HttpResponse, Error>
+where
+    F: FnMut(&str, u16, &str) -> Result<HttpResponse, Error>,
+{
+    let (mut host, mut port, mut path) = (host, port, path);
+    for _ in 0..=MAX_REDIRECTS {
+        let response = send(&host, port, &path)?;
+        match redirect_target(&response, &host, port, &path)? {
+            Some((next_host, next_port, next_path)) => {
+                host = next_host;
+                port = next_port;
+                path = next_path;
+            }
+            None => return Ok(response),
+        }
+    }
+
+    Err(Error::Network(format!(
+        "too many redirects (more than {})",
+        MAX_REDIRECTS
+    )))
+}
+
+// method のリクエストに対してこれまでに受け取ったバイト列から、レスポンス全体のバイト数を求める
+// ヘッダーをまだ受け取り終えていないか、ボディの長さがヘッダーからわからなければ None
+pub fn expected_response_length(method: &str, received: &[u8]) -> Option<usize> {
+    let (header_end, body_start) = find_header_end(received, 0)?;
+    body_length(method, &received[..header_end]).map(|length| body_start + length)
+}
+
+// ヘッダーの終わりを示す空行を from 以降から探し、(ヘッダーの終わり, ボディの始まり) を返す
+fn find_header_end(received: &[u8], from: usize) -> Option<(usize, usize)> {
+    [&b"\r\n\r\n"[..], &b"\n\n"[..]]
+        .iter()
+        .filter_map(|sep| {
+            find_bytes(&received[from..], sep).map(|i| (from + i, from + i + sep.len()))
+        })
+        .min()
+}
+
+// ステータスラインとヘッダーから、ボディのバイト数を求める
+// HEAD への応答と、1xx、204、304 のレスポンスはボディを持たない
+// Transfer-Encoding: chunked のときは Content-Length を無視し、長さはわからないものとする
+fn body_length(method: &str, head: &[u8]) -> Option<usize> {
+    let head = core::str::from_utf8(head).ok()?;
+    let mut lines = head.split('\n');
+    let status_code = lines
+        .next()?
+        .split(' ')
+        .nth(1)
+        .and_then(|code| code.trim().parse::<u32>().ok());
+    if method.eq_ignore_ascii_case("HEAD") || matches!(status_code, Some(100..=199 | 204 | 304)) {
+        return Some(0);
+    }
+
+    let mut content_length = None;
+    for line in lines {
+        let (name, value) = match line.split_once(':') {
+            Some(header) => header,
+            None => continue,
+        };
+        let name = name.trim();
+        if name.eq_ignore_ascii_case("transfer-encoding")
+            && value
+                .split(',')
+                .any(|v| v.trim().eq_ignore_ascii_case("chunked"))
+        {
+            return None;
+        }
+        if name.eq_ignore_ascii_case("content-length") && content_length.is_none() {
+            content_length = value.trim().parse::<usize>().ok();
+        }
+    }
+    content_length
+}
+
+// method のリクエストへのレスポンスを read で読む。ボディの長さがわかれば受け取り終えたところで止め、
+// わからなければ read が 0 を返す (接続が閉じられる) まで読む
+pub fn read_response<F>(
+    method: &str,
+    mut read: F,
+    max_response_size: Option<usize>,
+) -> Result<Vec<u8>, Error>
+where
+    F: FnMut(&mut [u8]) -> Result<usize, Error>,
+{
+    let mut received = Vec::new();
+    let mut headers_done = false;
+    let mut expected_length = None;
+    loop {
+        let mut buf = [0u8; 4096];
+        let bytes_read = read(&mut buf)?;
+        if bytes_read == 0 {
+            break;
+        }
+        let previous_len = received.len();
+        received.extend_from_slice(&buf[..bytes_read]);
+
+        // 上限を超えたらそれ以上は受け取らない
+        if let Some(max) = max_response_size {
+            if received.len() > max {
+                return Err(Error::Network(format!(
+                    "response exceeds the maximum size of {} bytes",
+                    max
+                )));
+            }
+        }
+
+        // ヘッダーを受け取り終えるまで、新しく届いた部分から空行を探す
+        // 空行は前回の終わりをまたぐことがあるので、その分だけ戻って探す
+        if !headers_done {
+            if let Some((header_end, body_start)) =
+                find_header_end(&received, previous_len.saturating_sub(3))
+            {
+                headers_done = true;
+                expected_length =
+                    body_length(method, &received[..header_end]).map(|length| body_start + length);
+            }
+        }
+        if let Some(length) = expected_length {
+            if received.len() >= length {
+                received.truncate(length);
+                break;
+            }
+        }
+    }
+
+    Ok(received)
+}
+
 #[derive(Debug, Clone)]
 pub struct Header {
     name: String,
@@ -13,6 +317,14 @@ impl Header {
     pub fn new(name: String, value: String) -> Self {
         Self { name, value }
     }
+
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    pub fn value(&self) -> String {
+        self.value.clone()
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -21,48 +333,108 @@ pub struct HttpResponse {
     status_code: u32,
     reason: String,
     headers: Vec<Header>,
-    body: String,
+    body: Vec<u8>, // 受け取ったままのボディ。画像などのバイナリも含む
 }
 
 impl HttpResponse {
     pub fn new(raw_response: String) -> Result<Self, Error> {
-        let preprocessed_response = raw_response.trim_start().replace("\n\r", "\n");
+        Self::from_bytes(raw_response.as_bytes())
+    }
+
+    // 受け取ったバイト列からレスポンスを作る
+    // チャンクの境界は複数バイトの文字の途中にもありうるので、ボディはバイト列のまま連結して保持する
+    pub fn from_bytes(raw_response: &[u8]) -> Result<Self, Error> {
+        let start = raw_response
+            .iter()
+            .position(|b| !b.is_ascii_whitespace())
+            .unwrap_or(raw_response.len());
+        let raw = &raw_response[start..];
 
         // 改行で分割
-        let (status_line, remaining) = match preprocessed_response.split_once('\n') {
-            Some((s, r)) => (s, r),
+        let (status_line, remaining) = match raw.iter().position(|b| *b == b'\n') {
+            Some(i) => (&raw[..i], &raw[i + 1..]),
             None => {
                 return Err(Error::Network(format!(
                     "Invalid http response: {}",
-                    preprocessed_response
+                    String::from_utf8_lossy(raw)
                 )))
             }
         };
+        let status_line = match core::str::from_utf8(status_line) {
+            Ok(status_line) => status_line.trim_end_matches('\r'),
+            Err(e) => return Err(Error::Network(format!("Invalid status line: {}", e))),
+        };
 
-        let (headers, body) = match remaining.split_once("\n\n") {
-            Some((h, b)) => {
+        // ステータスラインは "HTTP/1.1 200 OK" の形式。理由句は空白を含むことがある
+        let mut statuses = status_line.splitn(3, ' ');
+        let version = statuses.next().unwrap_or("");
+        let status_code = statuses.next().and_then(|code| code.parse::<u32>().ok());
+        let (version, status_code) = match status_code {
+            Some(code) if version.starts_with("HTTP/") => (version, code),
+            _ => {
+                return Err(Error::Network(format!(
+                    "Invalid status line: {}",
+                    status_line
+                )))
+            }
+        };
+        let reason = statuses.next().unwrap_or("");
+
+        // ヘッダーとボディは最初の空行で区切る。行末は CRLF と LF のどちらも受け付ける
+        // ボディの中の改行はそのまま残す
+        let separator = if remaining.starts_with(b"\r\n") {
+            Some((0, 2))
+        } else if remaining.starts_with(b"\n") {
+            Some((0, 1))
+        } else {
+            [(&b"\r\n\r\n"[..], 2), (&b"\n\n"[..], 1)]
+                .iter()
+                .filter_map(|(sep, header_end)| {
+                    find_bytes(remaining, sep).map(|i| (i + header_end, i + sep.len()))
+                })
+                .min()
+        };
+
+        let (headers, body) = match separator {
+            Some((header_end, body_start)) => {
+                let head = match core::str::from_utf8(&remaining[..header_end]) {
+                    Ok(head) => head,
+                    Err(e) => return Err(Error::Network(format!("Invalid headers: {}", e))),
+                };
                 let mut headers = Vec::new();
-                for header in h.split('\n') {
-                    let splitted_header: Vec<&str> = header.splitn(2, ':').collect();
-                    headers.push(Header::new(
-                        String::from(splitted_header[0].trim()),
-                        String::from(splitted_header[1].trim()),
-                    ))
+                for header in head.split('\n') {
+                    // ':' のない行はヘッダーとして扱わない
+                    if let Some((name, value)) = header.split_once(':') {
+                        headers.push(Header::new(
+                            String::from(name.trim()),
+                            String::from(value.trim()),
+                        ))
+                    }
                 }
-                (headers, b)
+                (headers, &remaining[body_start..])
             }
             // ヘッダーがなけらば空のベクターを返す
             None => (Vec::new(), remaining),
         };
 
-        let statuses: Vec<&str> = status_line.split(' ').collect();
-
+        // Transfer-Encoding: chunked ならチャンクを連結したものをボディとする
+        let chunked = headers.iter().any(|h| {
+            h.name.eq_ignore_ascii_case("transfer-encoding")
+                && h.value
+                    .split(',')
+                    .any(|v| v.trim().eq_ignore_ascii_case("chunked"))
+        });
+        let body = if chunked {
+            decode_chunked(body)?
+        } else {
+            body.to_vec()
+        };
         Ok(Self {
-            version: statuses[0].to_string(),
-            status_code: statuses[1].parse().unwrap_or(404),
-            reason: statuses[2].to_string(),
+            version: version.to_string(),
+            status_code,
+            reason: reason.to_string(),
             headers,
-            body: body.to_string(),
+            body,
         })
     }
 
@@ -82,23 +454,93 @@ impl HttpResponse {
         self.headers.clone()
     }
 
+    // 描画に使う文字列としてのボディ。UTF-8 として不正なバイトは U+FFFD に置き換える
     pub fn body(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+
+    // ダウンロードなどに使う、受け取ったままのボディ
+    pub fn body_bytes(&self) -> Vec<u8> {
         self.body.clone()
     }
 
+    // ヘッダー名は大文字と小文字を区別しない
     pub fn header_value(&self, name: &str) -> Result<String, String> {
         for h in &self.headers {
-            if h.name == name {
+            if h.name.eq_ignore_ascii_case(name) {
                 return Ok(h.value.clone());
             }
         }
         Err(format!("failed to find {} in headers", name))
     }
+
+    // 同じ名前のヘッダーの値を、レスポンスに現れた順にすべて返す
+    pub fn header_values(&self, name: &str) -> Vec<String> {
+        self.headers
+            .iter()
+            .filter(|h| h.name.eq_ignore_ascii_case(name))
+            .map(|h| h.value.clone())
+            .collect()
+    }
+
+    // 複数回現れたヘッダーの値をカンマ区切りで1つにまとめる
+    // Set-Cookie は値の中にカンマを含みうるため、まとめることはできない
+    pub fn combined_header_value(&self, name: &str) -> Result<String, String> {
+        if name.eq_ignore_ascii_case("set-cookie") {
+            return Err(format!("{} headers must not be combined", name));
+        }
+
+        let values = self.header_values(name);
+        if values.is_empty() {
+            return Err(format!("failed to find {} in headers", name));
+        }
+        Ok(values.join(", "))
+    }
+}
+
+// "<16進数のサイズ>\r\n<データ>\r\n" の並びを、サイズが 0 のチャンクまで連結する
+// サイズの後の拡張 (";name=value") と、最後のチャンクの後のトレーラーは無視する
+fn decode_chunked(body: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut decoded = Vec::new();
+    let mut remaining = body;
+
+    loop {
+        let (size_line, rest) = match remaining.iter().position(|b| *b == b'\n') {
+            Some(i) => (&remaining[..i], &remaining[i + 1..]),
+            None => return Err(Error::Network("unterminated chunked body".to_string())),
+        };
+        let size_line = core::str::from_utf8(size_line).unwrap_or("");
+        let size = size_line.split(';').next().unwrap_or("").trim();
+        let size = match usize::from_str_radix(size, 16) {
+            Ok(size) => size,
+            Err(_) => return Err(Error::Network(format!("Invalid chunk size: {}", size))),
+        };
+        if size == 0 {
+            return Ok(decoded);
+        }
+
+        match rest.get(..size) {
+            Some(data) => decoded.extend_from_slice(data),
+            None => return Err(Error::Network("truncated chunk".to_string())),
+        }
+        // データの後の改行を読み飛ばす
+        let rest = &rest[size..];
+        remaining = rest
+            .strip_prefix(b"\r\n")
+            .or_else(|| rest.strip_prefix(b"\n"))
+            .unwrap_or(rest);
+    }
+}
+
+// haystack の中で needle が最初に現れる位置
+fn find_bytes(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use alloc::vec;
 
     #[test]
     fn test_status_line_only() {
@@ -132,6 +574,30 @@ mod tests {
         assert_eq!(res.header_value("Content-Length"), Ok("42".to_string()));
     }
 
+    #[test]
+    fn test_header_name_is_case_insensitive() {
+        let raw =
+            "HTTP/1.1 200 OK\nContent-Type: text/html\nvary: Accept\nVARY: Cookie\n\n".to_string();
+        let res = HttpResponse::new(raw).expect("failed to parse response");
+
+        assert_eq!(
+            res.header_value("content-type"),
+            Ok("text/html".to_string())
+        );
+        assert_eq!(
+            res.header_value("CONTENT-TYPE"),
+            Ok("text/html".to_string())
+        );
+        assert_eq!(
+            res.header_values("Vary"),
+            vec!["Accept".to_string(), "Cookie".to_string()]
+        );
+        assert_eq!(
+            res.combined_header_value("vary"),
+            Ok("Accept, Cookie".to_string())
+        );
+    }
+
     #[test]
     fn test_body() {
         let raw = "HTTP/1.1 200 OK\nDate: xx xx xx\n\nbody message".to_string();
@@ -144,6 +610,455 @@ mod tests {
         assert_eq!(res.body(), "body message");
     }
 
+    #[test]
+    fn test_multiple_values() {
+        let raw = "HTTP/1.1 200 OK\nCache-Control: no-cache\nSet-Cookie: a=1\nCache-Control: max-age=0\nSet-Cookie: b=2\n\n".to_string();
+        let res = HttpResponse::new(raw).expect("failed to parse response");
+
+        assert_eq!(
+            res.header_value("Cache-Control"),
+            Ok("no-cache".to_string())
+        );
+        assert_eq!(
+            res.header_values("Cache-Control"),
+            vec!["no-cache".to_string(), "max-age=0".to_string()]
+        );
+        assert_eq!(
+            res.combined_header_value("Cache-Control"),
+            Ok("no-cache, max-age=0".to_string())
+        );
+
+        assert_eq!(
+            res.header_values("Set-Cookie"),
+            vec!["a=1".to_string(), "b=2".to_string()]
+        );
+        assert!(res.combined_header_value("Set-Cookie").is_err());
+        assert!(res.header_values("Date").is_empty());
+    }
+
+    #[test]
+    fn test_build_head_request() {
+        let request = build_request("HEAD", "example.com", "index.html");
+        assert!(request.starts_with("HEAD /index.html HTTP/1.1\r\n"));
+        assert!(request.contains("Host: example.com\r\n"));
+        assert!(request.ends_with("\r\n\r\n"));
+    }
+
+    #[test]
+    fn test_build_request_crlf() {
+        let request = build_request("GET", "example.com", "index.html");
+        assert_eq!(
+            "GET /index.html HTTP/1.1\r\nHost: example.com\r\nAccept: text/html\r\nConnection: close\r\n\r\n",
+            request
+        );
+    }
+
+    #[test]
+    fn test_build_request_single_slash() {
+        for path in ["/test.html", "test.html", "//test.html"] {
+            let request = build_request("GET", "example.com", path);
+            assert!(request.starts_with("GET /test.html HTTP/1.1\r\n"));
+        }
+        assert!(build_request("GET", "example.com", "").starts_with("GET / HTTP/1.1\r\n"));
+    }
+
+    #[test]
+    fn test_build_request_with_headers() {
+        let request = build_request_with_headers(
+            "GET",
+            "example.com",
+            "index.html",
+            &[Header::new(
+                "If-None-Match".to_string(),
+                "\"v1\"".to_string(),
+            )],
+        );
+        assert!(request.contains("Connection: close\r\nIf-None-Match: \"v1\"\r\n"));
+        assert!(request.ends_with("\r\n\r\n"));
+    }
+
+    #[test]
+    fn test_headers_only() {
+        let raw = "HTTP/1.1 200 OK\nContent-Length: 42\n\n".to_string();
+        let res = HttpResponse::new(raw).expect("failed to parse response");
+        assert_eq!(res.status_code(), 200);
+        assert_eq!(res.header_value("Content-Length"), Ok("42".to_string()));
+        assert_eq!(res.body(), "");
+    }
+
+    #[test]
+    fn test_mime_guess() {
+        assert_eq!("text/html", mime_guess("html"));
+        assert_eq!("application/xhtml+xml", mime_guess("xhtml"));
+        assert_eq!("text/css", mime_guess("css"));
+        assert_eq!("text/javascript", mime_guess("js"));
+        assert_eq!("text/plain", mime_guess("txt"));
+        assert_eq!("application/octet-stream", mime_guess("png"));
+    }
+
+    #[test]
+    fn test_crlf_response() {
+        let raw = "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: 11\r\n\r\nline1\r\nline2"
+            .to_string();
+        let res = HttpResponse::new(raw).expect("failed to parse response");
+        assert_eq!(res.version(), "HTTP/1.1");
+        assert_eq!(res.status_code(), 200);
+        assert_eq!(res.reason(), "OK");
+        assert_eq!(
+            res.header_value("Content-Type"),
+            Ok("text/html".to_string())
+        );
+        assert_eq!(res.header_value("Content-Length"), Ok("11".to_string()));
+        assert_eq!(res.body(), "line1\r\nline2");
+    }
+
+    #[test]
+    fn test_reason_with_spaces() {
+        let raw = "HTTP/1.1 304 Not Modified\r\n\r\n".to_string();
+        let res = HttpResponse::new(raw).expect("failed to parse response");
+        assert_eq!(res.status_code(), 304);
+        assert_eq!(res.reason(), "Not Modified");
+        assert_eq!(res.body(), "");
+    }
+
+    #[test]
+    fn test_invalid_status_line() {
+        for raw in [
+            "garbage\r\n\r\n",
+            "HTTP/1.1\r\n\r\n",
+            "HTTP/1.1 abc OK\r\n\r\n",
+        ] {
+            assert!(matches!(
+                HttpResponse::new(raw.to_string()),
+                Err(Error::Network(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_redirect_target() {
+        let res = HttpResponse::new(
+            "HTTP/1.1 302 Found\r\nLocation: http://example.org:8080/new/page.html?a=1\r\n\r\n"
+                .to_string(),
+        )
+        .expect("failed to parse response");
+        assert_eq!(
+            Ok(Some((
+                "example.org".to_string(),
+                8080,
+                "new/page.html?a=1".to_string()
+            ))),
+            redirect_target(&res, "example.com", 80, "old.html")
+        );
+
+        let res =
+            HttpResponse::new("HTTP/1.1 301 Moved Permanently\nLocation: /top\n\n".to_string())
+                .expect("failed to parse response");
+        assert_eq!(
+            Ok(Some(("example.com".to_string(), 8000, "top".to_string()))),
+            redirect_target(&res, "example.com", 8000, "dir/old.html")
+        );
+
+        let res = HttpResponse::new(
+            "HTTP/1.1 307 Temporary Redirect\nLocation: new.html\n\n".to_string(),
+        )
+        .expect("failed to parse response");
+        assert_eq!(
+            Ok(Some((
+                "example.com".to_string(),
+                80,
+                "dir/new.html".to_string()
+            ))),
+            redirect_target(&res, "example.com", 80, "dir/old.html")
+        );
+
+        let res = HttpResponse::new("HTTP/1.1 200 OK\nLocation: /top\n\n".to_string())
+            .expect("failed to parse response");
+        assert_eq!(Ok(None), redirect_target(&res, "example.com", 80, ""));
+    }
+
+    #[test]
+    fn test_redirect_scheme_relative_location() {
+        let res =
+            HttpResponse::new("HTTP/1.1 302 Found\nLocation: //example.org/x\n\n".to_string())
+                .expect("failed to parse response");
+        assert_eq!(
+            Ok(Some(("example.org".to_string(), 80, "x".to_string()))),
+            redirect_target(&res, "example.com", 8000, "dir/old.html")
+        );
+    }
+
+    #[test]
+    fn test_redirect_unsupported_scheme() {
+        for location in [
+            "https://example.org/x",
+            "ftp://example.org/x",
+            "mailto:a@example.org",
+        ] {
+            let res = HttpResponse::new(format!("HTTP/1.1 302 Found\nLocation: {}\n\n", location))
+                .expect("failed to parse response");
+            assert!(matches!(
+                redirect_target(&res, "example.com", 80, "dir/old.html"),
+                Err(Error::Network(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_redirect_without_location() {
+        let res = HttpResponse::new("HTTP/1.1 302 Found\n\n".to_string())
+            .expect("failed to parse response");
+        assert!(matches!(
+            redirect_target(&res, "example.com", 80, ""),
+            Err(Error::Network(_))
+        ));
+    }
+
+    #[test]
+    fn test_follow_redirects() {
+        let mut requests = Vec::new();
+        let res = follow_redirects(
+            "example.com".to_string(),
+            80,
+            "a".to_string(),
+            |host, port, path| {
+                requests.push(format!("{}:{}/{}", host, port, path));
+                let raw = match path {
+                    "a" => "HTTP/1.1 302 Found\r\nLocation: /b\r\n\r\n",
+                    "b" => "HTTP/1.1 303 See Other\r\nLocation: http://example.org/c\r\n\r\n",
+                    _ => "HTTP/1.1 200 OK\r\n\r\ndone",
+                };
+                HttpResponse::new(raw.to_string())
+            },
+        )
+        .expect("failed to follow redirects");
+
+        assert_eq!("done", res.body());
+        assert_eq!(
+            vec![
+                "example.com:80/a".to_string(),
+                "example.com:80/b".to_string(),
+                "example.org:80/c".to_string(),
+            ],
+            requests
+        );
+    }
+
+    #[test]
+    fn test_too_many_redirects() {
+        let mut count = 0;
+        let res = follow_redirects("example.com".to_string(), 80, "".to_string(), |_, _, _| {
+            count += 1;
+            HttpResponse::new("HTTP/1.1 302 Found\r\nLocation: /\r\n\r\n".to_string())
+        });
+
+        assert!(matches!(res, Err(Error::Network(_))));
+        assert_eq!(MAX_REDIRECTS + 1, count);
+    }
+
+    #[test]
+    fn test_chunked_body() {
+        let raw = "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nHello\r\n8;ext=1\r\n, world!\r\nA\r\n line\r\nend\r\n0\r\nExpires: never\r\n\r\n"
+            .to_string();
+        let res = HttpResponse::new(raw).expect("failed to parse response");
+        assert_eq!("Hello, world! line\r\nend", res.body());
+    }
+
+    #[test]
+    fn test_chunk_boundary_inside_character() {
+        // "あ" (E3 81 82) を2つのチャンクに分けて送る
+        let mut raw = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n".to_vec();
+        raw.extend_from_slice(b"2\r\n\xe3\x81\r\n1\r\n\x82\r\n0\r\n\r\n");
+        assert!(core::str::from_utf8(&raw).is_err());
+
+        let res = HttpResponse::from_bytes(&raw).expect("failed to parse response");
+        assert_eq!("あ", res.body());
+    }
+
+    #[test]
+    fn test_binary_body() {
+        let mut raw = b"HTTP/1.1 200 OK\r\nContent-Type: image/png\r\n\r\n".to_vec();
+        raw.extend_from_slice(b"\x89PNG\r\n\x1a\n\xff\x00");
+        let res = HttpResponse::from_bytes(&raw).expect("failed to parse response");
+        assert_eq!(b"\x89PNG\r\n\x1a\n\xff\x00".to_vec(), res.body_bytes());
+        assert_eq!("\u{fffd}PNG\r\n\u{1a}\n\u{fffd}\u{0}", res.body());
+    }
+
+    #[test]
+    fn test_invalid_chunked_body() {
+        for body in [
+            "zz\r\nabc\r\n0\r\n\r\n",
+            "10\r\nshort\r\n",
+            "5\r\nHello\r\n",
+        ] {
+            let raw = format!(
+                "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n{}",
+                body
+            );
+            assert!(matches!(HttpResponse::new(raw), Err(Error::Network(_))));
+        }
+    }
+
+    #[test]
+    fn test_expected_response_length() {
+        assert_eq!(
+            None,
+            expected_response_length("GET", b"HTTP/1.1 200 OK\r\nContent-Len")
+        );
+        assert_eq!(
+            None,
+            expected_response_length(
+                "GET",
+                b"HTTP/1.1 200 OK\r\nContent-Type: text/html\r\n\r\nabc"
+            )
+        );
+        assert_eq!(
+            Some(41),
+            expected_response_length("GET", b"HTTP/1.1 200 OK\r\ncontent-length: 3\r\n\r\na")
+        );
+        assert_eq!(
+            Some(35),
+            expected_response_length("GET", b"HTTP/1.1 200 OK\nContent-Length: 0\n\n")
+        );
+    }
+
+    #[test]
+    fn test_read_response_content_length() {
+        // keep-alive のように接続が閉じられず、最後まで読むとエラーになるストリーム
+        let chunks = [
+            &b"HTTP/1.1 200 OK\r\nContent-Le"[..],
+            &b"ngth: 5\r\n\r\nhel"[..],
+            &b"loEXTRA"[..],
+        ];
+        let mut i = 0;
+        let received = read_response(
+            "GET",
+            |buf| {
+                let chunk = match chunks.get(i) {
+                    Some(chunk) => chunk,
+                    None => return Err(Error::Network("read past the response".to_string())),
+                };
+                i += 1;
+                buf[..chunk.len()].copy_from_slice(chunk);
+                Ok(chunk.len())
+            },
+            None,
+        )
+        .expect("failed to read response");
+
+        assert_eq!(3, i);
+        let res = HttpResponse::new(String::from_utf8(received).expect("invalid utf-8"))
+            .expect("failed to parse response");
+        assert_eq!("hello", res.body());
+    }
+
+    #[test]
+    fn test_read_response_header_end_across_reads() {
+        let chunks = [
+            &b"HTTP/1.1 200 OK\r\nContent-Length: 2\r"[..],
+            &b"\n\r"[..],
+            &b"\nokEXTRA"[..],
+        ];
+        let mut i = 0;
+        let received = read_response(
+            "GET",
+            |buf| {
+                let chunk = match chunks.get(i) {
+                    Some(chunk) => chunk,
+                    None => return Err(Error::Network("read past the response".to_string())),
+                };
+                i += 1;
+                buf[..chunk.len()].copy_from_slice(chunk);
+                Ok(chunk.len())
+            },
+            None,
+        )
+        .expect("failed to read response");
+
+        assert_eq!(
+            b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok".to_vec(),
+            received
+        );
+    }
+
+    #[test]
+    fn test_expected_response_length_without_body() {
+        let head = b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\n";
+        assert_eq!(Some(head.len()), expected_response_length("HEAD", head));
+        assert_eq!(Some(head.len() + 5), expected_response_length("GET", head));
+
+        for status in ["100 Continue", "204 No Content", "304 Not Modified"] {
+            let head = format!("HTTP/1.1 {}\r\nContent-Length: 5\r\n\r\n", status);
+            assert_eq!(
+                Some(head.len()),
+                expected_response_length("GET", head.as_bytes())
+            );
+        }
+
+        assert_eq!(
+            None,
+            expected_response_length(
+                "GET",
+                b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\nTransfer-Encoding: chunked\r\n\r\n"
+            )
+        );
+    }
+
+    #[test]
+    fn test_read_response_head() {
+        // HEAD への応答は Content-Length があってもボディを持たないので、ヘッダーだけで止める
+        let chunks = [&b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\n"[..]];
+        let mut i = 0;
+        let received = read_response(
+            "HEAD",
+            |buf| {
+                let chunk = match chunks.get(i) {
+                    Some(chunk) => chunk,
+                    None => return Err(Error::Network("read past the response".to_string())),
+                };
+                i += 1;
+                buf[..chunk.len()].copy_from_slice(chunk);
+                Ok(chunk.len())
+            },
+            None,
+        )
+        .expect("failed to read response");
+
+        assert_eq!(chunks[0].to_vec(), received);
+    }
+
+    #[test]
+    fn test_read_response_until_close() {
+        let chunks = [&b"HTTP/1.1 200 OK\r\n\r\nbo"[..], &b"dy"[..], &b""[..]];
+        let mut i = 0;
+        let received = read_response(
+            "GET",
+            |buf| {
+                let chunk = chunks[i];
+                i += 1;
+                buf[..chunk.len()].copy_from_slice(chunk);
+                Ok(chunk.len())
+            },
+            None,
+        )
+        .expect("failed to read response");
+
+        assert_eq!(b"HTTP/1.1 200 OK\r\n\r\nbody".to_vec(), received);
+    }
+
+    #[test]
+    fn test_read_response_max_size() {
+        let res = read_response(
+            "GET",
+            |buf| {
+                buf[..8].copy_from_slice(b"HTTP/1.1");
+                Ok(8)
+            },
+            Some(4),
+        );
+        assert!(matches!(res, Err(Error::Network(_))));
+    }
+
     #[test]
     fn test_invalid() {
         let raw = "HTTP/1.1 200 OK".to_string();
@@ -1,5 +1,8 @@
+use crate::error::Error;
 use crate::renderer::css::token::CssToken;
 use crate::renderer::css::token::CssTokenizer;
+use alloc::boxed::Box;
+use alloc::format;
 use alloc::string::String;
 use alloc::{string::ToString, vec::Vec};
 use core::iter::Peekable;
@@ -9,11 +12,20 @@ pub type ComponentValue = CssToken;
 #[derive(Debug, Clone)]
 pub struct CssParser {
     t: Peekable<CssTokenizer>,
+    errors: Vec<Error>,
 }
 
 impl CssParser {
     pub fn new(t: CssTokenizer) -> Self {
-        Self { t: t.peekable() }
+        Self {
+            t: t.peekable(),
+            errors: Vec::new(),
+        }
+    }
+
+    // 解析中に読み飛ばした不正な入力の記録
+    pub fn errors(&self) -> Vec<Error> {
+        self.errors.clone()
     }
 
     pub fn parse_stylesheet(&mut self) -> StyleSheet {
@@ -33,9 +45,7 @@ impl CssParser {
             };
 
             match token {
-                CssToken::AtKeyword(_keyword) => {
-                    let _rule = self.consume_qualified_rule();
-                }
+                CssToken::AtKeyword(_keyword) => self.consume_at_rule(),
                 _ => {
                     let rule = self.consume_qualified_rule();
                     match rule {
@@ -74,34 +84,162 @@ impl CssParser {
         };
 
         match token {
-            CssToken::HashToken(value) => Selector::IdSelector(value[1..].to_string()),
+            CssToken::HashToken(value) => Selector::IdSelector(value.to_string()),
+            CssToken::Delim('*') => Selector::UniversalSelector,
             CssToken::Delim(delim) => {
                 if delim == '.' {
-                    return Selector::ClassSelector(self.consume_ident());
+                    if let Some(CssToken::Ident(_)) = self.t.peek() {
+                        return Selector::ClassSelector(self.consume_ident());
+                    }
                 }
-                panic!("Parse error: {:?} is an unexpected token.", token);
+                // 解釈できないセレクタは、宣言ブロックの手前まで読み飛ばす
+                self.errors.push(Error::UnexpectedInput(format!(
+                    "{:?} is an unexpected token in a selector",
+                    token
+                )));
+                self.skip_to_block();
+                Selector::UnknownSelector
+            }
+            // :root と :not() 擬似クラス。それ以外の擬似クラスは未対応
+            CssToken::Colon => {
+                let selector = self.consume_pseudo_class(false);
+                if selector == Selector::UnknownSelector {
+                    self.skip_to_block();
+                }
+                selector
             }
             CssToken::Ident(ident) => {
-                if self.t.peek() == Some(&CssToken::Colon) {
-                    while self.t.peek() != Some(&CssToken::OpenCurly) {
-                        self.t.next();
+                let type_selector = Selector::TypeSelector(ident.to_string());
+                if self.t.peek() != Some(&CssToken::Colon) {
+                    return type_selector;
+                }
+
+                // p:not(.x) のような、要素型と擬似クラスの組み合わせ
+                self.t.next();
+                match self.consume_pseudo_class(false) {
+                    Selector::UnknownSelector => {
+                        self.skip_to_block();
+                        type_selector
                     }
+                    pseudo => Selector::CompoundSelector([type_selector, pseudo].to_vec()),
                 }
-                Selector::TypeSelector(ident.to_string())
             }
-            CssToken::AtKeyword(_keyword) => {
-                while self.t.peek() != Some(&CssToken::OpenCurly) {
-                    self.t.next();
-                }
+            CssToken::AtKeyword(_) => {
+                self.errors.push(Error::UnexpectedInput(format!(
+                    "{:?} is an unexpected token in a selector",
+                    token
+                )));
+                self.skip_to_block();
                 Selector::UnknownSelector
             }
             _ => {
-                self.t.next();
+                self.errors.push(Error::UnexpectedInput(format!(
+                    "{:?} is an unexpected token in a selector",
+                    token
+                )));
                 Selector::UnknownSelector
             }
         }
     }
 
+    // @import や @media などの at-rule は未対応なので読み飛ばす
+    // ';' で終わるか、'{' が続く場合は対応する '}' までの1つのブロックを読み飛ばす
+    fn consume_at_rule(&mut self) {
+        let keyword = self.t.next();
+        self.errors.push(Error::UnexpectedInput(format!(
+            "{:?} is an unsupported at-rule",
+            keyword
+        )));
+
+        loop {
+            match self.t.peek() {
+                // '}' は外側の規則のものなので消費しない
+                None | Some(CssToken::CloseCurly) => return,
+                Some(CssToken::SemiColon) => {
+                    self.t.next();
+                    return;
+                }
+                Some(CssToken::OpenCurly) => {
+                    self.skip_block();
+                    return;
+                }
+                _ => {
+                    self.t.next();
+                }
+            }
+        }
+    }
+
+    // '{' から対応する '}' までを、入れ子のブロックも含めて読み飛ばす
+    fn skip_block(&mut self) {
+        let mut depth = 0;
+        for token in self.t.by_ref() {
+            match token {
+                CssToken::OpenCurly => depth += 1,
+                CssToken::CloseCurly => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // ':' の後に続く擬似クラスを読む。nested が true なら :not() の中
+    fn consume_pseudo_class(&mut self, nested: bool) -> Selector {
+        let ident = match self.t.peek() {
+            Some(CssToken::Ident(ident)) => ident.clone(),
+            _ => return Selector::UnknownSelector,
+        };
+
+        match ident.as_str() {
+            "root" => {
+                self.t.next();
+                Selector::RootSelector
+            }
+            "not" => {
+                self.t.next();
+                if self.t.next() != Some(CssToken::OpenParenthesis) {
+                    return Selector::UnknownSelector;
+                }
+                if nested {
+                    self.errors.push(Error::UnexpectedInput(
+                        ":not() cannot be nested".to_string(),
+                    ));
+                    return Selector::UnknownSelector;
+                }
+
+                let inner = match self.t.next() {
+                    Some(CssToken::HashToken(value)) => Selector::IdSelector(value.to_string()),
+                    Some(CssToken::Delim('.')) => match self.t.peek() {
+                        Some(CssToken::Ident(_)) => Selector::ClassSelector(self.consume_ident()),
+                        _ => Selector::UnknownSelector,
+                    },
+                    Some(CssToken::Ident(ident)) => Selector::TypeSelector(ident),
+                    Some(CssToken::Delim('*')) => Selector::UniversalSelector,
+                    Some(CssToken::Colon) => self.consume_pseudo_class(true),
+                    _ => Selector::UnknownSelector,
+                };
+                if inner == Selector::UnknownSelector
+                    || self.t.next() != Some(CssToken::CloseParenthesis)
+                {
+                    return Selector::UnknownSelector;
+                }
+                Selector::NotSelector(Box::new(inner))
+            }
+            _ => Selector::UnknownSelector,
+        }
+    }
+
+    // 次の '{' の手前まで読み進める
+    fn skip_to_block(&mut self) {
+        while self.t.peek().is_some() && self.t.peek() != Some(&CssToken::OpenCurly) {
+            self.t.next();
+        }
+    }
+
     fn consume_list_of_declarations(&mut self) -> Vec<Declaration> {
         let mut declarations = Vec::new();
 
@@ -141,17 +279,109 @@ impl CssParser {
         let mut declaration = Declaration::new();
         declaration.set_property(self.consume_ident());
 
-        match self.t.next() {
-            Some(token) => match token {
-                CssToken::Colon => {}
-                _ => return None,
-            },
-            None => return None,
+        // プロパティ名の後に ':' がない場合は、その宣言だけを読み飛ばす
+        match self.t.peek() {
+            Some(CssToken::Colon) => {
+                self.t.next();
+            }
+            token => {
+                self.errors.push(Error::UnexpectedInput(format!(
+                    "expected ':' after property {:?} but got {:?}",
+                    declaration.property, token
+                )));
+                self.skip_declaration();
+                return None;
+            }
+        }
+
+        if matches!(
+            self.t.peek(),
+            None | Some(CssToken::SemiColon) | Some(CssToken::CloseCurly)
+        ) {
+            self.errors.push(Error::UnexpectedInput(format!(
+                "property {:?} doesn't have a value",
+                declaration.property
+            )));
+            return None;
+        }
+
+        if self.t.peek() == Some(&CssToken::Ident("var".to_string())) {
+            self.t.next();
+            let var_reference = self.consume_var_reference()?;
+            if let Some(fallback) = &var_reference.fallback {
+                declaration.set_value(fallback.clone());
+            }
+            declaration.set_var_reference(var_reference);
+        } else {
+            declaration.set_value(self.consume_component_value());
+        }
+
+        // "12px" や "1px solid red" のように複数のトークンからなる値は、最初のトークンだけを使う
+        while !matches!(
+            self.t.peek(),
+            None | Some(CssToken::SemiColon)
+                | Some(CssToken::CloseCurly)
+                | Some(CssToken::Delim('!'))
+        ) {
+            self.t.next();
         }
-        declaration.set_value(self.consume_component_value());
+
+        if self.t.peek() == Some(&CssToken::Delim('!')) {
+            self.t.next();
+            match self.t.peek() {
+                Some(CssToken::Ident(ident)) if ident.eq_ignore_ascii_case("important") => {
+                    self.t.next();
+                    declaration.important = true;
+                }
+                // "!" の後に important が続かない宣言は無効として読み飛ばす
+                _ => {
+                    self.errors.push(Error::UnexpectedInput(format!(
+                        "expected 'important' after '!' in property {:?}",
+                        declaration.property
+                    )));
+                    self.skip_declaration();
+                    return None;
+                }
+            }
+        }
+
         Some(declaration)
     }
 
+    // 次の ';' または '}' の手前まで読み進める
+    fn skip_declaration(&mut self) {
+        while let Some(token) = self.t.peek() {
+            if token == &CssToken::SemiColon || token == &CssToken::CloseCurly {
+                return;
+            }
+            self.t.next();
+        }
+    }
+
+    // var(--name) または var(--name, fallback) を解析する
+    fn consume_var_reference(&mut self) -> Option<VarReference> {
+        if self.t.next() != Some(CssToken::OpenParenthesis) {
+            return None;
+        }
+
+        let name = match self.t.next() {
+            Some(CssToken::Ident(name)) if name.starts_with("--") => name,
+            _ => return None,
+        };
+
+        let mut fallback = None;
+        if self.t.peek() == Some(&CssToken::Delim(',')) {
+            self.t.next();
+            fallback = self.t.next();
+        }
+
+        if self.t.next() != Some(CssToken::CloseParenthesis) {
+            return None;
+        }
+
+        Some(VarReference { name, fallback })
+    }
+
     fn consume_ident(&mut self) -> String {
         let token = match self.t.next() {
             Some(t) => t,
@@ -190,6 +420,7 @@ impl StyleSheet {
 pub struct QualifiedRule {
     pub selector: Selector,
     pub declarations: Vec<Declaration>,
+    pub origin: Origin,
 }
 
 impl QualifiedRule {
@@ -197,9 +428,14 @@ impl QualifiedRule {
         Self {
             selector: Selector::TypeSelector("".to_string()),
             declarations: Vec::new(),
+            origin: Origin::Author,
         }
     }
 
+    pub fn set_origin(&mut self, origin: Origin) {
+        self.origin = origin;
+    }
+
     pub fn set_selector(&mut self, selector: Selector) {
         self.selector = selector;
     }
@@ -209,18 +445,66 @@ impl QualifiedRule {
     }
 }
 
+// ルールがどのスタイルシートから来たか
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Origin {
+    Author, // ページ自身のスタイルシート
+    User,   // ユーザーが指定したスタイルシート
+}
+
+// カスケードでの優先順位。値が大きいほど優先される
+// 作成者の通常の宣言 < ユーザーの通常の宣言 < 作成者の !important < ユーザーの !important
+pub fn cascade_tier(origin: Origin, important: bool) -> u32 {
+    match (important, origin) {
+        (false, Origin::Author) => 0,
+        (false, Origin::User) => 1,
+        (true, Origin::Author) => 2,
+        (true, Origin::User) => 3,
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Selector {
     TypeSelector(String),
     ClassSelector(String),
     IdSelector(String),
+    UniversalSelector,               // *
+    RootSelector,                    // :root
+    NotSelector(Box<Selector>),      // :not(...)
+    CompoundSelector(Vec<Selector>), // p:not(.x) のように、すべてにマッチする必要がある
     UnknownSelector,
 }
 
+// セレクタの詳細度を (id の数, クラスと擬似クラスの数, 要素型の数) で返す
+// 比較するときはタプルの辞書順で大きいほうが優先される
+pub fn specificity(selector: &Selector) -> (u32, u32, u32) {
+    match selector {
+        Selector::IdSelector(_) => (1, 0, 0),
+        Selector::ClassSelector(_) | Selector::RootSelector => (0, 1, 0),
+        Selector::TypeSelector(_) => (0, 0, 1),
+        // :not() 自体は数えず、引数のセレクタの詳細度になる
+        Selector::NotSelector(inner) => specificity(inner),
+        Selector::CompoundSelector(selectors) => selectors.iter().fold((0, 0, 0), |acc, s| {
+            let (a, b, c) = specificity(s);
+            (acc.0 + a, acc.1 + b, acc.2 + c)
+        }),
+        Selector::UniversalSelector | Selector::UnknownSelector => (0, 0, 0),
+    }
+}
+
+// var() によるカスタムプロパティの参照。値はカスケード時に解決される
+#[derive(Debug, Clone, PartialEq)]
+pub struct VarReference {
+    pub name: String,
+    pub fallback: Option<ComponentValue>,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Declaration {
     pub property: String,
     pub value: ComponentValue,
+    pub var_reference: Option<VarReference>,
+    pub important: bool, // !important が指定されているかどうか
 }
 
 impl Declaration {
@@ -228,9 +512,16 @@ impl Declaration {
         Self {
             property: String::new(),
             value: ComponentValue::Ident(String::new()),
+            var_reference: None,
+            important: false,
         }
     }
 
+    // "--" で始まるプロパティはカスタムプロパティ（CSS 変数）
+    pub fn is_custom_property(&self) -> bool {
+        self.property.starts_with("--")
+    }
+
     pub fn set_property(&mut self, property: String) {
         self.property = property;
     }
@@ -238,6 +529,10 @@ impl Declaration {
     pub fn set_value(&mut self, value: ComponentValue) {
         self.value = value;
     }
+
+    pub fn set_var_reference(&mut self, var_reference: VarReference) {
+        self.var_reference = Some(var_reference);
+    }
 }
 
 #[cfg(test)]
@@ -356,4 +651,264 @@ mod tests {
             i += 1;
         }
     }
+
+    #[test]
+    fn test_root_and_custom_property() {
+        let style = ":root { --main: red; } p { color: var(--main, blue); }".to_string();
+        let t = CssTokenizer::new(style);
+        let cssom = CssParser::new(t).parse_stylesheet();
+
+        let mut rule1 = QualifiedRule::new();
+        rule1.set_selector(Selector::RootSelector);
+        let mut declaration1 = Declaration::new();
+        declaration1.set_property("--main".to_string());
+        declaration1.set_value(ComponentValue::Ident("red".to_string()));
+        rule1.set_declarations(vec![declaration1]);
+
+        let mut rule2 = QualifiedRule::new();
+        rule2.set_selector(Selector::TypeSelector("p".to_string()));
+        let mut declaration2 = Declaration::new();
+        declaration2.set_property("color".to_string());
+        declaration2.set_value(ComponentValue::Ident("blue".to_string()));
+        declaration2.set_var_reference(VarReference {
+            name: "--main".to_string(),
+            fallback: Some(ComponentValue::Ident("blue".to_string())),
+        });
+        rule2.set_declarations(vec![declaration2]);
+
+        let expected = [rule1, rule2];
+        assert_eq!(cssom.rules.len(), expected.len());
+
+        for (i, rule) in cssom.rules.iter().enumerate() {
+            assert_eq!(&expected[i], rule);
+        }
+    }
+
+    #[test]
+    fn test_malformed_declaration() {
+        let style = "p { color red; background: blue; }".to_string();
+        let t = CssTokenizer::new(style);
+        let mut parser = CssParser::new(t);
+        let cssom = parser.parse_stylesheet();
+
+        let mut rule = QualifiedRule::new();
+        rule.set_selector(Selector::TypeSelector("p".to_string()));
+        let mut declaration = Declaration::new();
+        declaration.set_property("background".to_string());
+        declaration.set_value(ComponentValue::Ident("blue".to_string()));
+        rule.set_declarations(vec![declaration]);
+
+        assert_eq!(vec![rule], cssom.rules);
+
+        let errors = parser.errors();
+        assert_eq!(1, errors.len());
+        match &errors[0] {
+            Error::UnexpectedInput(message) => assert!(message.contains("color")),
+            e => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn test_missing_colon_in_last_declaration() {
+        let style = "p { color red; } a { color: blue; }".to_string();
+        let t = CssTokenizer::new(style);
+        let mut parser = CssParser::new(t);
+        let cssom = parser.parse_stylesheet();
+
+        assert_eq!(2, cssom.rules.len());
+        assert!(cssom.rules[0].declarations.is_empty());
+        assert_eq!(
+            Selector::TypeSelector("a".to_string()),
+            cssom.rules[1].selector
+        );
+        assert_eq!("color", cssom.rules[1].declarations[0].property);
+        assert_eq!(1, parser.errors().len());
+    }
+
+    #[test]
+    fn test_declaration_without_value() {
+        let style = "p { color: ; background: blue; }".to_string();
+        let t = CssTokenizer::new(style);
+        let mut parser = CssParser::new(t);
+        let cssom = parser.parse_stylesheet();
+
+        assert_eq!(1, cssom.rules[0].declarations.len());
+        assert_eq!("background", cssom.rules[0].declarations[0].property);
+        assert_eq!(1, parser.errors().len());
+    }
+
+    #[test]
+    fn test_malformed_rule() {
+        let style = "@@bad { } p { color: red; }".to_string();
+        let t = CssTokenizer::new(style);
+        let mut parser = CssParser::new(t);
+        let cssom = parser.parse_stylesheet();
+
+        let mut rule = QualifiedRule::new();
+        rule.set_selector(Selector::TypeSelector("p".to_string()));
+        let mut declaration = Declaration::new();
+        declaration.set_property("color".to_string());
+        declaration.set_value(ComponentValue::Ident("red".to_string()));
+        rule.set_declarations(vec![declaration]);
+
+        assert_eq!(Some(&rule), cssom.rules.last());
+        assert_eq!(1, parser.errors().len());
+    }
+
+    #[test]
+    fn test_at_rule_at_eof() {
+        let style = "@import \"a.css\"".to_string();
+        let t = CssTokenizer::new(style);
+        let mut parser = CssParser::new(t);
+        let cssom = parser.parse_stylesheet();
+
+        assert!(cssom.rules.is_empty());
+        assert_eq!(1, parser.errors().len());
+    }
+
+    #[test]
+    fn test_rule_after_at_rule() {
+        let style =
+            "@import \"a.css\"; @media screen { p { color: blue; } } p { color: red; }".to_string();
+        let t = CssTokenizer::new(style);
+        let mut parser = CssParser::new(t);
+        let cssom = parser.parse_stylesheet();
+
+        assert_eq!(1, cssom.rules.len());
+        assert_eq!(
+            Selector::TypeSelector("p".to_string()),
+            cssom.rules[0].selector
+        );
+        assert_eq!(
+            ComponentValue::Ident("red".to_string()),
+            cssom.rules[0].declarations[0].value
+        );
+        assert_eq!(2, parser.errors().len());
+    }
+
+    #[test]
+    fn test_stray_close_curly() {
+        let style = "} p { color: red; }".to_string();
+        let t = CssTokenizer::new(style);
+        let mut parser = CssParser::new(t);
+        let cssom = parser.parse_stylesheet();
+
+        assert_eq!(1, cssom.rules.len());
+        assert_eq!(
+            Selector::TypeSelector("p".to_string()),
+            cssom.rules[0].selector
+        );
+        assert_eq!(1, parser.errors().len());
+    }
+
+    #[test]
+    fn test_not_selector() {
+        let style = "p:not(.x) { color: red; }".to_string();
+        let t = CssTokenizer::new(style);
+        let mut parser = CssParser::new(t);
+        let cssom = parser.parse_stylesheet();
+
+        assert_eq!(1, cssom.rules.len());
+        assert_eq!(
+            Selector::CompoundSelector(
+                [
+                    Selector::TypeSelector("p".to_string()),
+                    Selector::NotSelector(Box::new(Selector::ClassSelector("x".to_string()))),
+                ]
+                .to_vec()
+            ),
+            cssom.rules[0].selector
+        );
+        assert!(parser.errors().is_empty());
+    }
+
+    #[test]
+    fn test_nested_not_selector() {
+        let style = ":not(:not(.x)) { color: red; } p { color: blue; }".to_string();
+        let t = CssTokenizer::new(style);
+        let mut parser = CssParser::new(t);
+        let cssom = parser.parse_stylesheet();
+
+        assert_eq!(2, cssom.rules.len());
+        assert_eq!(Selector::UnknownSelector, cssom.rules[0].selector);
+        assert_eq!(
+            Selector::TypeSelector("p".to_string()),
+            cssom.rules[1].selector
+        );
+        assert_eq!(1, parser.errors().len());
+    }
+
+    #[test]
+    fn test_important() {
+        let style = "p { color: red !important; background-color: blue ! ; }".to_string();
+        let t = CssTokenizer::new(style);
+        let mut parser = CssParser::new(t);
+        let cssom = parser.parse_stylesheet();
+
+        let mut declaration = Declaration::new();
+        declaration.set_property("color".to_string());
+        declaration.set_value(ComponentValue::Ident("red".to_string()));
+        declaration.important = true;
+
+        assert_eq!(vec![declaration], cssom.rules[0].declarations);
+        assert_eq!(1, parser.errors().len());
+    }
+
+    #[test]
+    fn test_multiple_token_value() {
+        let style = "p { color: red; font-size: 12px; }".to_string();
+        let t = CssTokenizer::new(style);
+        let mut parser = CssParser::new(t);
+        let cssom = parser.parse_stylesheet();
+
+        let mut color = Declaration::new();
+        color.set_property("color".to_string());
+        color.set_value(ComponentValue::Ident("red".to_string()));
+        let mut font_size = Declaration::new();
+        font_size.set_property("font-size".to_string());
+        font_size.set_value(ComponentValue::Number(12.0));
+
+        assert_eq!(1, cssom.rules.len());
+        assert_eq!(vec![color, font_size], cssom.rules[0].declarations);
+        assert!(parser.errors().is_empty());
+    }
+
+    #[test]
+    fn test_universal_selector() {
+        let style = "* { color: red; }".to_string();
+        let t = CssTokenizer::new(style);
+        let mut parser = CssParser::new(t);
+        let cssom = parser.parse_stylesheet();
+
+        assert_eq!(1, cssom.rules.len());
+        assert_eq!(Selector::UniversalSelector, cssom.rules[0].selector);
+        assert!(parser.errors().is_empty());
+    }
+
+    #[test]
+    fn test_specificity() {
+        assert_eq!(
+            (1, 0, 0),
+            specificity(&Selector::IdSelector("x".to_string()))
+        );
+        assert_eq!(
+            (0, 1, 0),
+            specificity(&Selector::ClassSelector("c".to_string()))
+        );
+        assert_eq!(
+            (0, 0, 1),
+            specificity(&Selector::TypeSelector("p".to_string()))
+        );
+        assert_eq!((0, 0, 0), specificity(&Selector::UniversalSelector));
+        assert_eq!(
+            (0, 1, 1),
+            specificity(&Selector::CompoundSelector(
+                [
+                    Selector::TypeSelector("p".to_string()),
+                    Selector::NotSelector(Box::new(Selector::ClassSelector("x".to_string()))),
+                ]
+                .to_vec()
+            ))
+        );
+    }
 }
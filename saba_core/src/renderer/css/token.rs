@@ -4,7 +4,7 @@ use alloc::vec::Vec;
 // CSS のトークン（本来は 24 種類ある）
 #[derive(Debug, Clone, PartialEq)]
 pub enum CssToken {
-    HashToken(String),
+    HashToken(String), // '#' の後に続く値。'#' は含まない
     Delim(char),
     Number(f64),
     Colon,
@@ -16,6 +16,7 @@ pub enum CssToken {
     Ident(String),
     StringToken(String),
     AtKeyword(String),
+    Url(String), // url(...) の中身。引用符は含まない
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -55,6 +56,53 @@ impl CssTokenizer {
         s
     }
 
+    // "url(" の '(' から対応する ')' までを消費し、中の URL を返す
+    // URL は引用符で囲まれていてもいなくてもよく、前後の空白は無視する
+    fn consume_url_token(&mut self) -> String {
+        // skip '('
+        self.pos += 1;
+        self.skip_whitespace();
+
+        let url = match self.input.get(self.pos) {
+            Some('"') | Some('\'') => {
+                let value = self.consume_string_token();
+                // 終了引用符をスキップする
+                self.pos += 1;
+                value
+            }
+            _ => {
+                let mut s = String::new();
+                while let Some(&c) = self.input.get(self.pos) {
+                    if c == ')' || c.is_ascii_whitespace() {
+                        break;
+                    }
+                    s.push(c);
+                    self.pos += 1;
+                }
+                s
+            }
+        };
+
+        // 閉じ括弧までを読み飛ばす
+        while let Some(&c) = self.input.get(self.pos) {
+            self.pos += 1;
+            if c == ')' {
+                break;
+            }
+        }
+        url
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self
+            .input
+            .get(self.pos)
+            .is_some_and(|c| c.is_ascii_whitespace())
+        {
+            self.pos += 1;
+        }
+    }
+
     // 数字またはピリオドが出続けている間、数字として解釈する
     // それ以外が来たら数字を返すメソッド
     fn consume_numeric_token(&mut self) -> f64 {
@@ -89,6 +137,20 @@ impl CssTokenizer {
         num
     }
 
+    // "/*" から "*/" までを読み飛ばす。閉じられていなければ入力の最後まで読み飛ばす
+    fn skip_comment(&mut self) {
+        // skip "/*"
+        self.pos += 2;
+
+        while self.pos < self.input.len() {
+            if self.input[self.pos] == '*' && self.input.get(self.pos + 1) == Some(&'/') {
+                self.pos += 2;
+                return;
+            }
+            self.pos += 1;
+        }
+    }
+
     // 文字、数字、ハイフンまたはアンダースコアが出続けている間、識別子として解釈する
     // それ以外が出てきたら今までの文字を返してメソッドを終了
     fn consume_ident_token(&mut self) -> String {
@@ -97,6 +159,11 @@ impl CssTokenizer {
 
         loop {
             self.pos += 1;
+            // 識別子が入力の最後で終わる場合
+            if self.pos >= self.input.len() {
+                break;
+            }
+
             let c = self.input[self.pos];
             match c {
                 'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' => {
@@ -136,6 +203,25 @@ impl Iterator for CssTokenizer {
                     self.pos += 1;
                     CssToken::Delim('.')
                 }
+                // 全称セレクタの '*'
+                '*' => {
+                    self.pos += 1;
+                    CssToken::Delim('*')
+                }
+                // "!important" の '!'
+                '!' => {
+                    self.pos += 1;
+                    CssToken::Delim('!')
+                }
+                // "/* ... */" のコメントはトークンを生成せずに読み飛ばす
+                '/' => {
+                    if self.input.get(self.pos + 1) == Some(&'*') {
+                        self.skip_comment();
+                        continue;
+                    }
+                    self.pos += 1;
+                    CssToken::Delim('/')
+                }
                 ':' => {
                     self.pos += 1;
                     CssToken::Colon
@@ -164,29 +250,43 @@ impl Iterator for CssTokenizer {
                     CssToken::StringToken(value)
                 }
                 '0'..='9' => CssToken::Number(self.consume_numeric_token()),
-                // 常に #ID のセレクタとして扱う
+                // #id のセレクタ、または #aabbcc のような色の値
                 '#' => {
-                    let value = self.consume_ident_token();
-                    CssToken::HashToken(value)
+                    let starts_ident = self
+                        .input
+                        .get(self.pos + 1)
+                        .is_some_and(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_');
+                    // skip '#'
+                    self.pos += 1;
+                    if starts_ident {
+                        CssToken::HashToken(self.consume_ident_token())
+                    } else {
+                        CssToken::Delim('#')
+                    }
                 }
                 '-' => CssToken::Ident(self.consume_ident_token()),
-                // 次の3文字が識別子として有効な場合、 at-keyword-token を生成する
+                // '@' の直後が識別子の開始として有効な場合、 at-keyword-token を生成する
                 '@' => {
-                    if self.pos + 3 < self.input.len()
-                        && self.input[self.pos + 1].is_ascii_alphabetic()
-                        && self.input[self.pos + 2].is_ascii_alphabetic()
-                        && self.input[self.pos + 3].is_ascii_alphabetic()
-                    {
-                        // skip '@'
-                        self.pos += 1;
-                        let t = CssToken::AtKeyword(self.consume_string_token());
-                        t
+                    let starts_ident = self
+                        .input
+                        .get(self.pos + 1)
+                        .is_some_and(|c| c.is_ascii_alphabetic() || *c == '-' || *c == '_');
+                    // skip '@'
+                    self.pos += 1;
+                    if starts_ident {
+                        CssToken::AtKeyword(self.consume_ident_token())
                     } else {
-                        self.pos += 1;
                         CssToken::Delim('@')
                     }
                 }
-                'a'..='z' | 'A'..='Z' | '_' => CssToken::Ident(self.consume_ident_token()),
+                'a'..='z' | 'A'..='Z' | '_' => {
+                    let ident = self.consume_ident_token();
+                    if ident.eq_ignore_ascii_case("url") && self.input.get(self.pos) == Some(&'(') {
+                        CssToken::Url(self.consume_url_token())
+                    } else {
+                        CssToken::Ident(ident)
+                    }
+                }
                 _ => {
                     unimplemented!("char {} is not supported yet", c);
                 }
@@ -233,7 +333,7 @@ mod tests {
         let style = "#id { color: red; }".to_string();
         let mut t = CssTokenizer::new(style);
         let expected = [
-            CssToken::HashToken("#id".to_string()),
+            CssToken::HashToken("id".to_string()),
             CssToken::OpenCurly,
             CssToken::Ident("color".to_string()),
             CssToken::Colon,
@@ -267,6 +367,128 @@ mod tests {
         assert!(t.next().is_none());
     }
 
+    #[test]
+    fn test_important() {
+        let style = "color: red !important;".to_string();
+        let mut t = CssTokenizer::new(style);
+        let expected = [
+            CssToken::Ident("color".to_string()),
+            CssToken::Colon,
+            CssToken::Ident("red".to_string()),
+            CssToken::Delim('!'),
+            CssToken::Ident("important".to_string()),
+            CssToken::SemiColon,
+        ];
+        for e in expected {
+            assert_eq!(Some(e.clone()), t.next());
+        }
+        assert!(t.next().is_none());
+    }
+
+    #[test]
+    fn test_at_keyword() {
+        let style = "@media screen { } @".to_string();
+        let mut t = CssTokenizer::new(style);
+        let expected = [
+            CssToken::AtKeyword("media".to_string()),
+            CssToken::Ident("screen".to_string()),
+            CssToken::OpenCurly,
+            CssToken::CloseCurly,
+            CssToken::Delim('@'),
+        ];
+        for e in expected {
+            assert_eq!(Some(e.clone()), t.next());
+        }
+        assert!(t.next().is_none());
+    }
+
+    #[test]
+    fn test_url() {
+        let style =
+            "a { background: url(img.png); b: url(\"img.png\"); c: url( 'a b.png' ); }".to_string();
+        let urls: Vec<CssToken> = CssTokenizer::new(style)
+            .filter(|t| matches!(t, CssToken::Url(_)))
+            .collect();
+        assert_eq!(
+            [
+                CssToken::Url("img.png".to_string()),
+                CssToken::Url("img.png".to_string()),
+                CssToken::Url("a b.png".to_string()),
+            ]
+            .to_vec(),
+            urls
+        );
+    }
+
+    #[test]
+    fn test_hash_color() {
+        let style = "color: #aabbcc;".to_string();
+        let mut t = CssTokenizer::new(style);
+        let expected = [
+            CssToken::Ident("color".to_string()),
+            CssToken::Colon,
+            CssToken::HashToken("aabbcc".to_string()),
+            CssToken::SemiColon,
+        ];
+        for e in expected {
+            assert_eq!(Some(e.clone()), t.next());
+        }
+        assert!(t.next().is_none());
+    }
+
+    #[test]
+    fn test_ident_at_end_of_input() {
+        let style = "color:red".to_string();
+        let mut t = CssTokenizer::new(style);
+        let expected = [
+            CssToken::Ident("color".to_string()),
+            CssToken::Colon,
+            CssToken::Ident("red".to_string()),
+        ];
+        for e in expected {
+            assert_eq!(Some(e.clone()), t.next());
+        }
+        assert!(t.next().is_none());
+    }
+
+    #[test]
+    fn test_comment() {
+        let style = "p { /* note */ color: red; }".to_string();
+        let mut t = CssTokenizer::new(style);
+        let expected = [
+            CssToken::Ident("p".to_string()),
+            CssToken::OpenCurly,
+            CssToken::Ident("color".to_string()),
+            CssToken::Colon,
+            CssToken::Ident("red".to_string()),
+            CssToken::SemiColon,
+            CssToken::CloseCurly,
+        ];
+        for e in expected {
+            assert_eq!(Some(e.clone()), t.next());
+        }
+        assert!(t.next().is_none());
+    }
+
+    #[test]
+    fn test_unterminated_comment() {
+        let style = "p { color: red; } /* no end *".to_string();
+        let mut t = CssTokenizer::new(style);
+        let expected = [
+            CssToken::Ident("p".to_string()),
+            CssToken::OpenCurly,
+            CssToken::Ident("color".to_string()),
+            CssToken::Colon,
+            CssToken::Ident("red".to_string()),
+            CssToken::SemiColon,
+            CssToken::CloseCurly,
+        ];
+        for e in expected {
+            assert_eq!(Some(e.clone()), t.next());
+        }
+        assert!(t.next().is_none());
+    }
+
     #[test]
     fn test_multiple_rules() {
         let style = "p { content: \"Hey\"; } h1 { font-size: 40; color: blue; }".to_string();
@@ -1,8 +1,11 @@
+use crate::renderer::dom::api::get_target_element_node;
 use crate::renderer::html::attribute::Attribute;
+use crate::renderer::html::token::is_html_whitespace;
 use alloc::format;
 use alloc::rc::Rc;
 use alloc::rc::Weak;
 use alloc::string::String;
+use alloc::string::ToString;
 use alloc::vec::Vec;
 use core::cell::RefCell;
 use core::fmt::{Display, Formatter};
@@ -12,13 +15,35 @@ use core::str::FromStr;
 pub enum ElementKind {
     Html,
     Head,
+    Title,
     Style,
     Script,
     Body,
     P,
     H1,
     H2,
+    H3,
+    H4,
+    H5,
+    H6,
     A,
+    Code,
+    Pre,
+    Img,
+    Ul,
+    Ol,
+    Li,
+    Div,
+    Span,
+    Br,
+    Table,
+    Form,
+    Button,
+    Label,
+    Select,
+    Option,
+    // カスタム要素など、未対応のタグ名を持つ要素
+    Unknown,
 }
 
 impl FromStr for ElementKind {
@@ -28,13 +53,33 @@ impl FromStr for ElementKind {
         match s {
             "html" => Ok(ElementKind::Html),
             "head" => Ok(ElementKind::Head),
+            "title" => Ok(ElementKind::Title),
             "style" => Ok(ElementKind::Style),
             "script" => Ok(ElementKind::Script),
             "body" => Ok(ElementKind::Body),
             "p" => Ok(ElementKind::P),
             "h1" => Ok(ElementKind::H1),
             "h2" => Ok(ElementKind::H2),
+            "h3" => Ok(ElementKind::H3),
+            "h4" => Ok(ElementKind::H4),
+            "h5" => Ok(ElementKind::H5),
+            "h6" => Ok(ElementKind::H6),
             "a" => Ok(ElementKind::A),
+            "code" => Ok(ElementKind::Code),
+            "pre" => Ok(ElementKind::Pre),
+            "img" => Ok(ElementKind::Img),
+            "ul" => Ok(ElementKind::Ul),
+            "ol" => Ok(ElementKind::Ol),
+            "li" => Ok(ElementKind::Li),
+            "div" => Ok(ElementKind::Div),
+            "span" => Ok(ElementKind::Span),
+            "br" => Ok(ElementKind::Br),
+            "table" => Ok(ElementKind::Table),
+            "form" => Ok(ElementKind::Form),
+            "button" => Ok(ElementKind::Button),
+            "label" => Ok(ElementKind::Label),
+            "select" => Ok(ElementKind::Select),
+            "option" => Ok(ElementKind::Option),
             _ => Err(format!("unimplemented element name: {:?}", s)),
         }
     }
@@ -45,36 +90,79 @@ impl Display for ElementKind {
         let s = match self {
             ElementKind::Html => "html",
             ElementKind::Head => "head",
+            ElementKind::Title => "title",
             ElementKind::Style => "style",
             ElementKind::Script => "script",
             ElementKind::Body => "body",
             ElementKind::H1 => "h1",
             ElementKind::H2 => "h2",
+            ElementKind::H3 => "h3",
+            ElementKind::H4 => "h4",
+            ElementKind::H5 => "h5",
+            ElementKind::H6 => "h6",
             ElementKind::P => "p",
             ElementKind::A => "a",
+            ElementKind::Code => "code",
+            ElementKind::Pre => "pre",
+            ElementKind::Img => "img",
+            ElementKind::Ul => "ul",
+            ElementKind::Ol => "ol",
+            ElementKind::Li => "li",
+            ElementKind::Div => "div",
+            ElementKind::Span => "span",
+            ElementKind::Br => "br",
+            ElementKind::Table => "table",
+            ElementKind::Form => "form",
+            ElementKind::Button => "button",
+            ElementKind::Label => "label",
+            ElementKind::Select => "select",
+            ElementKind::Option => "option",
+            ElementKind::Unknown => "unknown",
         };
         write!(f, "{}", s)
     }
 }
 
+// 終了タグを持たない要素のタグ名
+pub static VOID_ELEMENTS: [&str; 13] = [
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Element {
     kind: ElementKind,
+    tag_name: String,
     attributes: Vec<Attribute>,
 }
 
 impl Element {
     pub fn new(element_name: &str, attributes: Vec<Attribute>) -> Self {
         Self {
-            kind: ElementKind::from_str(element_name)
-                .expect("failed to convert string to ElementKind"),
+            // 未対応のタグ名は汎用の要素として扱う
+            kind: ElementKind::from_str(element_name).unwrap_or(ElementKind::Unknown),
+            tag_name: element_name.to_string(),
             attributes,
         }
     }
 
     pub fn is_block_element(&self) -> bool {
         match self.kind {
-            ElementKind::Body | ElementKind::H1 | ElementKind::H2 | ElementKind::P => true,
+            ElementKind::Body
+            | ElementKind::H1
+            | ElementKind::H2
+            | ElementKind::H3
+            | ElementKind::H4
+            | ElementKind::H5
+            | ElementKind::H6
+            | ElementKind::P
+            | ElementKind::Pre
+            | ElementKind::Ul
+            | ElementKind::Ol
+            | ElementKind::Li
+            | ElementKind::Div
+            | ElementKind::Table
+            | ElementKind::Form => true,
             _ => false,
         }
     }
@@ -83,9 +171,104 @@ impl Element {
         self.kind
     }
 
+    // h1 から h6 までの見出し要素かどうか
+    pub fn is_heading_element(&self) -> bool {
+        matches!(
+            self.kind,
+            ElementKind::H1
+                | ElementKind::H2
+                | ElementKind::H3
+                | ElementKind::H4
+                | ElementKind::H5
+                | ElementKind::H6
+        )
+    }
+
+    // 終了タグや子ノードを持たない要素かどうか
+    pub fn is_void_element(&self) -> bool {
+        VOID_ELEMENTS.contains(&self.tag_name.as_str())
+    }
+
+    pub fn tag_name(&self) -> String {
+        self.tag_name.clone()
+    }
+
     pub fn attributes(&self) -> Vec<Attribute> {
         self.attributes.clone()
     }
+
+    // disabled などの真偽値属性は、値に関係なく属性があるだけで有効になる
+    pub fn has_boolean_attribute(&self, name: &str) -> bool {
+        self.get_attribute(name).is_some()
+    }
+
+    // フォーム部品とリンクはフォーカスできるが、無効化されたフォーム部品はフォーカスできない
+    pub fn is_focusable(&self) -> bool {
+        match self.tag_name.as_str() {
+            "input" | "button" | "select" | "textarea" => !self.has_boolean_attribute("disabled"),
+            "a" => self.get_attribute("href").is_some(),
+            _ => false,
+        }
+    }
+
+    // チェックボックスとラジオボタンが選択されているかどうか
+    pub fn is_checked(&self) -> bool {
+        if self.tag_name != "input" {
+            return false;
+        }
+        match self.get_attribute("type") {
+            Some(t) if t == "checkbox" || t == "radio" => self.has_boolean_attribute("checked"),
+            _ => false,
+        }
+    }
+
+    // 指定した名前の属性の値を返す。属性がなければ None
+    pub fn get_attribute(&self, name: &str) -> Option<String> {
+        self.attributes
+            .iter()
+            .find(|attr| attr.name() == name)
+            .map(|attr| attr.value())
+    }
+
+    // form 要素の送信先。action 属性がなければ None
+    pub fn form_action(&self) -> Option<String> {
+        if self.kind != ElementKind::Form {
+            return None;
+        }
+        self.get_attribute("action")
+    }
+
+    // form 要素の送信メソッド。未指定や不正な値は "get" として扱う
+    pub fn form_method(&self) -> Option<String> {
+        if self.kind != ElementKind::Form {
+            return None;
+        }
+        match self.get_attribute("method") {
+            Some(method) if method.eq_ignore_ascii_case("post") => Some("post".to_string()),
+            _ => Some("get".to_string()),
+        }
+    }
+
+    // まだ持っていない名前の属性だけを追加する
+    pub fn merge_attributes(&mut self, attributes: Vec<Attribute>) {
+        for attr in attributes {
+            if self.get_attribute(&attr.name()).is_none() {
+                self.attributes.push(attr);
+            }
+        }
+    }
+
+    // class 属性を ASCII ホワイトスペースで区切ったクラス名の一覧
+    pub fn class_list(&self) -> Vec<String> {
+        match self.get_attribute("class") {
+            Some(class) => class
+                .split(is_html_whitespace)
+                .filter(|name| !name.is_empty())
+                .map(|name| name.to_string())
+                .collect(),
+            None => Vec::new(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Eq)]
@@ -100,7 +283,7 @@ impl PartialEq for NodeKind {
         match &self {
             NodeKind::Document => matches!(other, NodeKind::Document),
             NodeKind::Element(e1) => match &other {
-                NodeKind::Element(e2) => e1.kind == e2.kind,
+                NodeKind::Element(e2) => e1.kind == e2.kind && e1.tag_name == e2.tag_name,
                 _ => false,
             },
             NodeKind::Text(_) => matches!(other, NodeKind::Text(_)),
@@ -115,15 +298,17 @@ pub struct Window {
 }
 
 impl Window {
-    pub fn new() -> Self {
-        let window = Self {
+    pub fn new() -> Rc<RefCell<Self>> {
+        let window = Rc::new(RefCell::new(Self {
             document: Rc::new(RefCell::new(Node::new(NodeKind::Document))),
-        };
+        }));
 
+        // ドキュメントからウィンドウへの参照は、呼び出し元が持つ Rc を指すようにする
         window
+            .borrow()
             .document
             .borrow_mut()
-            .set_window(Rc::downgrade(&Rc::new(RefCell::new(window.clone()))));
+            .set_window(Rc::downgrade(&window));
 
         window
     }
@@ -132,6 +317,39 @@ impl Window {
     pub fn document(&self) -> Rc<RefCell<Node>> {
         self.document.clone()
     }
+
+    // id 属性が id と一致する最初の要素を、文書順に深さ優先で探す
+    pub fn get_element_by_id(&self, id: &str) -> Option<Rc<RefCell<Node>>> {
+        NodeIterator::new(self.document()).find(|n| match n.borrow().get_element() {
+            Some(e) => e.get_attribute("id").as_deref() == Some(id),
+            None => false,
+        })
+    }
+
+    // 指定したクラス名を持つ要素を、文書順にすべて返す
+    pub fn get_elements_by_class_name(&self, name: &str) -> Vec<Rc<RefCell<Node>>> {
+        NodeIterator::new(self.document())
+            .filter(|n| match n.borrow().get_element() {
+                Some(e) => e.class_list().iter().any(|c| c == name),
+                None => false,
+            })
+            .collect()
+    }
+
+    // head 内の title 要素のテキスト。連続する空白は1つにまとめる
+    pub fn title(&self) -> Option<String> {
+        let head = get_target_element_node(Some(self.document()), ElementKind::Head)?;
+        let title = get_target_element_node(head.borrow().first_child(), ElementKind::Title)?;
+
+        let text = match title.borrow().first_child() {
+            Some(child) => match child.borrow().kind() {
+                NodeKind::Text(text) => text,
+                _ => String::new(),
+            },
+            None => String::new(),
+        };
+        Some(text.split_whitespace().collect::<Vec<&str>>().join(" "))
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -169,6 +387,13 @@ impl Node {
         }
     }
 
+    pub fn get_element_mut(&mut self) -> Option<&mut Element> {
+        match self.kind {
+            NodeKind::Document | NodeKind::Text(_) => None,
+            NodeKind::Element(ref mut e) => Some(e),
+        }
+    }
+
     pub fn get_element_kind(&self) -> Option<ElementKind> {
         match self.kind {
             NodeKind::Document | NodeKind::Text(_) => None,
@@ -180,6 +405,17 @@ impl Node {
         self.window = window;
     }
 
+    pub fn window(&self) -> Weak<RefCell<Window>> {
+        self.window.clone()
+    }
+
+    // ノードが属するドキュメント。ウィンドウが既に破棄されていれば None
+    pub fn owner_document(&self) -> Option<Rc<RefCell<Node>>> {
+        self.window
+            .upgrade()
+            .map(|window| window.borrow().document())
+    }
+
     pub fn set_parent(&mut self, parent: Weak<RefCell<Node>>) {
         self.parent = parent;
     }
@@ -200,8 +436,9 @@ impl Node {
         self.last_child = last_child;
     }
 
-    pub fn last_child(&self) -> Weak<RefCell<Node>> {
-        self.last_child.clone()
+    // 弱い参照を持っているので、子ノードがすでに破棄されていれば None
+    pub fn last_child(&self) -> Option<Rc<RefCell<Node>>> {
+        self.last_child.upgrade()
     }
 
     pub fn set_previous_sibling(&mut self, previous_sibling: Weak<RefCell<Node>>) {
@@ -219,6 +456,185 @@ impl Node {
     pub fn next_sibling(&self) -> Option<Rc<RefCell<Node>>> {
         self.next_sibling.as_ref().cloned()
     }
+
+    // 子孫のテキストノードの内容を文書順に連結する。空白はそのまま残す
+    pub fn text_content(&self) -> String {
+        if let NodeKind::Text(ref text) = self.kind {
+            return text.clone();
+        }
+
+        let mut content = String::new();
+        let mut child = self.first_child();
+        while let Some(c) = child {
+            content.push_str(&c.borrow().text_content());
+            child = c.borrow().next_sibling();
+        }
+        content
+    }
+
+    // a と b を根とする部分木が同じ構造かどうか
+    // 種類、属性、テキストの内容、子ノードを再帰的に比べ、Rc の同一性と弱い参照は無視する
+    pub fn structurally_eq(a: &Rc<RefCell<Node>>, b: &Rc<RefCell<Node>>) -> bool {
+        let same_kind = match (&a.borrow().kind, &b.borrow().kind) {
+            (NodeKind::Document, NodeKind::Document) => true,
+            (NodeKind::Element(e1), NodeKind::Element(e2)) => e1 == e2,
+            (NodeKind::Text(t1), NodeKind::Text(t2)) => t1 == t2,
+            _ => false,
+        };
+        if !same_kind {
+            return false;
+        }
+
+        let mut child_a = a.borrow().first_child();
+        let mut child_b = b.borrow().first_child();
+        loop {
+            match (child_a, child_b) {
+                (Some(ca), Some(cb)) => {
+                    if !Node::structurally_eq(&ca, &cb) {
+                        return false;
+                    }
+                    child_a = ca.borrow().next_sibling();
+                    child_b = cb.borrow().next_sibling();
+                }
+                (None, None) => return true,
+                _ => return false,
+            }
+        }
+    }
+
+    // 子孫のテキストノードのうち、内容が predicate を満たすものを文書順にすべて返す
+    pub fn matches_text<F>(&self, predicate: F) -> Vec<Rc<RefCell<Node>>>
+    where
+        F: Fn(&str) -> bool,
+    {
+        let mut result = Vec::new();
+        collect_text_nodes(self.first_child(), &predicate, &mut result);
+        result
+    }
+
+    // パーサの往復テスト用に、部分木を HTML のソースに戻す
+    // 空要素は終了タグを付けずに出力する
+    pub fn to_html(&self) -> String {
+        self.outer_html()
+    }
+
+    // 子ノードを HTML の文字列に変換する（innerHTML）
+    pub fn inner_html(&self) -> String {
+        // <script> や <style> の中身はエスケープせずにそのまま出力する
+        let raw_text = matches!(
+            self.get_element_kind(),
+            Some(ElementKind::Script) | Some(ElementKind::Style)
+        );
+
+        let mut html = String::new();
+        let mut child = self.first_child();
+        while let Some(c) = child {
+            match c.borrow().kind {
+                NodeKind::Text(ref text) if raw_text => html.push_str(text),
+                _ => html.push_str(&c.borrow().outer_html()),
+            }
+            child = c.borrow().next_sibling();
+        }
+        html
+    }
+
+    // ノード自身を含めて HTML の文字列に変換する（outerHTML）
+    // 属性は追加された順に、値を二重引用符で囲んで出力する
+    pub fn outer_html(&self) -> String {
+        match self.kind {
+            NodeKind::Document => self.inner_html(),
+            NodeKind::Text(ref text) => escape_html(text, false),
+            NodeKind::Element(ref e) => {
+                let mut html = format!("<{}", e.tag_name());
+                for attr in e.attributes() {
+                    html.push_str(&format!(
+                        " {}=\"{}\"",
+                        attr.name(),
+                        escape_html(&attr.value(), true)
+                    ));
+                }
+                html.push('>');
+
+                // 空要素は終了タグを持たない
+                if e.is_void_element() {
+                    return html;
+                }
+
+                html.push_str(&self.inner_html());
+                html.push_str(&format!("</{}>", e.tag_name()));
+                html
+            }
+        }
+    }
+}
+
+fn collect_text_nodes<F>(
+    node: Option<Rc<RefCell<Node>>>,
+    predicate: &F,
+    result: &mut Vec<Rc<RefCell<Node>>>,
+) where
+    F: Fn(&str) -> bool,
+{
+    let mut child = node;
+    while let Some(c) = child {
+        if let NodeKind::Text(ref text) = c.borrow().kind {
+            if predicate(text) {
+                result.push(c.clone());
+            }
+        }
+        collect_text_nodes(c.borrow().first_child(), predicate, result);
+        child = c.borrow().next_sibling();
+    }
+}
+
+// HTML として出力するために特殊文字を文字参照に置き換える
+// 属性値の場合は '"' も置き換える
+fn escape_html(s: &str, is_attribute: bool) -> String {
+    let mut escaped = String::new();
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' if !is_attribute => escaped.push_str("&lt;"),
+            '>' if !is_attribute => escaped.push_str("&gt;"),
+            '"' if is_attribute => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// root とその子孫を深さ優先の行きがけ順で返すイテレータ
+// 子ノードは first_child と next_sibling だけをたどり、弱い参照の parent や
+// previous_sibling はたどらないので、同じノードを2回返すことはない
+pub struct NodeIterator {
+    stack: Vec<Rc<RefCell<Node>>>,
+}
+
+impl NodeIterator {
+    pub fn new(root: Rc<RefCell<Node>>) -> Self {
+        Self {
+            stack: [root].to_vec(),
+        }
+    }
+}
+
+impl Iterator for NodeIterator {
+    type Item = Rc<RefCell<Node>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.stack.pop()?;
+
+        // 最初の子ノードが先に取り出されるように、子ノードを逆順に積む
+        let mut children = Vec::new();
+        let mut child = node.borrow().first_child();
+        while let Some(c) = child {
+            child = c.borrow().next_sibling();
+            children.push(c);
+        }
+        self.stack.extend(children.into_iter().rev());
+
+        Some(node)
+    }
 }
 
 impl PartialEq for Node {
@@ -226,3 +642,374 @@ impl PartialEq for Node {
         self.kind == other.kind
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::html::parser::HtmlParser;
+    use crate::renderer::html::token::HtmlTokenizer;
+    use alloc::string::ToString;
+    use alloc::vec;
+
+    fn parse_body(html: &str) -> Rc<RefCell<Node>> {
+        let html = format!("<html><head></head><body>{}</body></html>", html);
+        let window = HtmlParser::new(HtmlTokenizer::new(html)).construct_tree();
+        let document = window.borrow().document();
+        get_target_element_node(Some(document), ElementKind::Body)
+            .expect("failed to get a body element")
+    }
+
+    #[test]
+    fn test_structurally_eq() {
+        let html = "<p class=\"a\">hi<span>there</span></p><p>end</p>";
+        let first = parse_body(html);
+        let second = parse_body(html);
+        assert!(!Rc::ptr_eq(&first, &second));
+        assert!(Node::structurally_eq(&first, &second));
+
+        // テキスト、属性の値、子ノードの数が違えば等しくない
+        assert!(!Node::structurally_eq(
+            &first,
+            &parse_body("<p class=\"a\">hi<span>here</span></p><p>end</p>")
+        ));
+        assert!(!Node::structurally_eq(
+            &first,
+            &parse_body("<p class=\"b\">hi<span>there</span></p><p>end</p>")
+        ));
+        assert!(!Node::structurally_eq(
+            &first,
+            &parse_body("<p class=\"a\">hi<span>there</span></p>")
+        ));
+    }
+
+    #[test]
+    fn test_outer_html() {
+        let body = parse_body("<div class=\"x\"><p>hi</p></div>");
+        let div = body
+            .borrow()
+            .first_child()
+            .expect("failed to get a first child of body");
+
+        assert_eq!(
+            "<div class=\"x\"><p>hi</p></div>".to_string(),
+            div.borrow().outer_html()
+        );
+        assert_eq!("<p>hi</p>".to_string(), div.borrow().inner_html());
+    }
+
+    #[test]
+    fn test_outer_html_escapes_text_and_void_element() {
+        let body = parse_body("<p><img src=\"x.png\">a & b</p>");
+        let p = body
+            .borrow()
+            .first_child()
+            .expect("failed to get a first child of body");
+
+        assert_eq!(
+            "<p><img src=\"x.png\">a &amp; b</p>".to_string(),
+            p.borrow().outer_html()
+        );
+    }
+
+    #[test]
+    fn test_get_attribute() {
+        let body = parse_body("<a href=\"http://x\">go</a>");
+        let a = body
+            .borrow()
+            .first_child()
+            .expect("failed to get a first child of body");
+        let element = a.borrow().get_element().expect("failed to get an element");
+
+        assert_eq!(ElementKind::A, element.kind());
+        assert_eq!(Some("http://x".to_string()), element.get_attribute("href"));
+        assert_eq!(None, element.get_attribute("title"));
+    }
+
+    #[test]
+    fn test_matches_text() {
+        let body = parse_body("<p>apple</p><div><p>banana</p></div><p>cherry</p>");
+        let texts: Vec<String> = body
+            .borrow()
+            .matches_text(|text| text.contains('a'))
+            .iter()
+            .map(|n| match n.borrow().kind() {
+                NodeKind::Text(text) => text,
+                _ => panic!("expected a text node: {:?}", n),
+            })
+            .collect();
+
+        assert_eq!(vec!["apple".to_string(), "banana".to_string()], texts);
+    }
+
+    #[test]
+    fn test_boolean_attribute() {
+        let body = parse_body("<input disabled><input type=\"checkbox\" checked=\"\">");
+        let disabled = body
+            .borrow()
+            .first_child()
+            .expect("failed to get a first child of body")
+            .borrow()
+            .get_element()
+            .expect("failed to get an element");
+        assert!(disabled.has_boolean_attribute("disabled"));
+        assert!(!disabled.has_boolean_attribute("readonly"));
+        assert!(!disabled.is_focusable());
+        assert!(!disabled.is_checked());
+
+        let checkbox = body
+            .borrow()
+            .first_child()
+            .expect("failed to get a first child of body")
+            .borrow()
+            .next_sibling()
+            .expect("failed to get a next sibling")
+            .borrow()
+            .get_element()
+            .expect("failed to get an element");
+        assert!(checkbox.is_focusable());
+        assert!(checkbox.is_checked());
+    }
+
+    #[test]
+    fn test_title() {
+        let html = "<html><head><meta charset=\"utf-8\"><title> Hello\n  World </title><link rel=\"stylesheet\" href=\"a.css\"></head><body></body></html>";
+        let window = HtmlParser::new(HtmlTokenizer::new(html.to_string())).construct_tree();
+        assert_eq!(Some("Hello World".to_string()), window.borrow().title());
+
+        let head = get_target_element_node(Some(window.borrow().document()), ElementKind::Head)
+            .expect("failed to get a head element");
+        assert_eq!(
+            "<meta charset=\"utf-8\"><title>Hello\n  World </title><link rel=\"stylesheet\" href=\"a.css\">"
+                .to_string(),
+            head.borrow().inner_html()
+        );
+    }
+
+    #[test]
+    fn test_no_title() {
+        let window = HtmlParser::new(HtmlTokenizer::new(
+            "<html><head></head><body>x</body></html>".to_string(),
+        ))
+        .construct_tree();
+        assert_eq!(None, window.borrow().title());
+    }
+
+    #[test]
+    fn test_get_element_by_id() {
+        let html = "<html><head></head><body><div><p id=\"x\">hi</p></div><p id=\"y\">bye</p></body></html>";
+        let window = HtmlParser::new(HtmlTokenizer::new(html.to_string())).construct_tree();
+
+        let x = window
+            .borrow()
+            .get_element_by_id("x")
+            .expect("failed to find an element with id x");
+        assert_eq!("<p id=\"x\">hi</p>".to_string(), x.borrow().outer_html());
+        assert!(window.borrow().get_element_by_id("missing").is_none());
+    }
+
+    #[test]
+    fn test_class_list() {
+        let body = parse_body("<p class=\"a  b\">x</p><p class=\"\">y</p><p>z</p>");
+        let p = body.borrow().first_child().expect("first p should exist");
+        assert_eq!(
+            ["a".to_string(), "b".to_string()].to_vec(),
+            p.borrow()
+                .get_element()
+                .expect("should be an element")
+                .class_list()
+        );
+
+        let empty = p.borrow().next_sibling().expect("second p should exist");
+        assert!(empty
+            .borrow()
+            .get_element()
+            .expect("should be an element")
+            .class_list()
+            .is_empty());
+        let missing = empty.borrow().next_sibling().expect("third p should exist");
+        assert!(missing
+            .borrow()
+            .get_element()
+            .expect("should be an element")
+            .class_list()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_get_elements_by_class_name() {
+        let html = "<html><head></head><body><p class=\"a b\">1</p><div class=\"b\"><span class=\"c\">2</span></div></body></html>";
+        let window = HtmlParser::new(HtmlTokenizer::new(html.to_string())).construct_tree();
+
+        let found: Vec<String> = window
+            .borrow()
+            .get_elements_by_class_name("b")
+            .iter()
+            .filter_map(|n| n.borrow().get_element().map(|e| e.tag_name()))
+            .collect();
+        assert_eq!(["p".to_string(), "div".to_string()].to_vec(), found);
+        assert!(window.borrow().get_elements_by_class_name("").is_empty());
+    }
+
+    #[test]
+    fn test_text_content() {
+        let body = parse_body("<p>Hello <b>world</b></p>");
+        assert_eq!("Hello world".to_string(), body.borrow().text_content());
+    }
+
+    #[test]
+    fn test_node_iterator() {
+        let body = parse_body("<div><p>a</p><p>b</p></div><span>c</span>");
+        let visited: Vec<String> = NodeIterator::new(body)
+            .map(|n| match n.borrow().kind() {
+                NodeKind::Document => "#document".to_string(),
+                NodeKind::Element(e) => e.tag_name(),
+                NodeKind::Text(text) => text,
+            })
+            .collect();
+
+        assert_eq!(
+            vec!["body", "div", "p", "a", "p", "b", "span", "c"]
+                .into_iter()
+                .map(|s| s.to_string())
+                .collect::<Vec<String>>(),
+            visited
+        );
+    }
+
+    #[test]
+    fn test_outer_html_attribute_order() {
+        let html = "<a id=\"top\" href=\"/x?a=1&b=2\" title='say \"hi\"'>x</a>";
+        let expected =
+            "<a id=\"top\" href=\"/x?a=1&amp;b=2\" title=\"say &quot;hi&quot;\">x</a>".to_string();
+
+        // 何度パースして出力しても同じ文字列になる
+        for _ in 0..3 {
+            let a = parse_body(html)
+                .borrow()
+                .first_child()
+                .expect("failed to get a first child of body");
+            assert_eq!(expected, a.borrow().outer_html());
+        }
+    }
+
+    #[test]
+    fn test_to_html_round_trip() {
+        for html in ["<p class=\"a\">hi</p>", "<p>a<br>b</p>"] {
+            let p = parse_body(html)
+                .borrow()
+                .first_child()
+                .expect("failed to get a first child of body");
+            assert_eq!(html.to_string(), p.borrow().to_html());
+        }
+    }
+
+    #[test]
+    fn test_document_window() {
+        let window =
+            HtmlParser::new(HtmlTokenizer::new("<html></html>".to_string())).construct_tree();
+        let document = window.borrow().document();
+
+        let upgraded = document
+            .borrow()
+            .window()
+            .upgrade()
+            .expect("failed to upgrade the window of the document");
+        assert!(Rc::ptr_eq(&window, &upgraded));
+    }
+
+    #[test]
+    fn test_node_owner_window() {
+        let window = HtmlParser::new(HtmlTokenizer::new(
+            "<html><body><p>hi</p></body></html>".to_string(),
+        ))
+        .construct_tree();
+        let document = window.borrow().document();
+        let body = get_target_element_node(Some(document.clone()), ElementKind::Body)
+            .expect("body should exist");
+        let p = body.borrow().first_child().expect("p should exist");
+        let text = p.borrow().first_child().expect("text should exist");
+
+        for node in [p, text] {
+            let upgraded = node
+                .borrow()
+                .window()
+                .upgrade()
+                .expect("failed to upgrade the window of the node");
+            assert!(Rc::ptr_eq(&window, &upgraded));
+
+            let owner = node
+                .borrow()
+                .owner_document()
+                .expect("node should have an owner document");
+            assert!(Rc::ptr_eq(&document, &owner));
+        }
+
+        // ウィンドウを手放すと、ノードからの参照は切れる（循環参照でリークしない）
+        drop(document);
+        drop(window);
+        assert!(body.borrow().window().upgrade().is_none());
+        assert!(body.borrow().owner_document().is_none());
+    }
+
+    #[test]
+    fn test_last_child() {
+        let body = parse_body("<p>a</p><div>b</div>text");
+
+        let mut last = None;
+        let mut child = body.borrow().first_child();
+        while let Some(c) = child {
+            child = c.borrow().next_sibling();
+            last = Some(c);
+        }
+
+        let last = last.expect("body should have children");
+        let last_child = body
+            .borrow()
+            .last_child()
+            .expect("failed to get a last child of body");
+        assert!(Rc::ptr_eq(&last, &last_child));
+        assert!(parse_body("").borrow().last_child().is_none());
+    }
+
+    #[test]
+    fn test_element_kind_from_str() {
+        let kinds = [
+            ElementKind::Html,
+            ElementKind::Head,
+            ElementKind::Title,
+            ElementKind::Style,
+            ElementKind::Script,
+            ElementKind::Body,
+            ElementKind::P,
+            ElementKind::H1,
+            ElementKind::H2,
+            ElementKind::H3,
+            ElementKind::H4,
+            ElementKind::H5,
+            ElementKind::H6,
+            ElementKind::A,
+            ElementKind::Code,
+            ElementKind::Pre,
+            ElementKind::Img,
+            ElementKind::Ul,
+            ElementKind::Ol,
+            ElementKind::Li,
+            ElementKind::Div,
+            ElementKind::Span,
+            ElementKind::Br,
+            ElementKind::Table,
+            ElementKind::Form,
+            ElementKind::Button,
+            ElementKind::Label,
+            ElementKind::Select,
+            ElementKind::Option,
+        ];
+        for kind in kinds {
+            assert_eq!(Ok(kind), ElementKind::from_str(&kind.to_string()));
+        }
+
+        // 未対応のタグ名はエラーになる
+        assert!(ElementKind::from_str("my-element").is_err());
+        assert!(ElementKind::from_str("unknown").is_err());
+    }
+}
@@ -1,6 +1,6 @@
-use crate::renderer::dom::node::{Element, ElementKind, Node, NodeKind, Window};
+use crate::renderer::dom::node::{Element, ElementKind, Node, NodeKind, Window, VOID_ELEMENTS};
 use crate::renderer::html::attribute::Attribute;
-use crate::renderer::html::token::{HTMLToken, HtmlTokenizer};
+use crate::renderer::html::token::{is_html_whitespace, HTMLToken, HtmlTokenizer};
 use alloc::rc::Rc;
 use alloc::string::String;
 use alloc::vec::Vec;
@@ -28,27 +28,41 @@ pub struct HtmlParser {
     original_insertion_mode: InsertionMode, // とある状態に遷移した時に以前の挿入モードを保存する
     stack_of_open_elements: Vec<Rc<RefCell<Node>>>, // 構文解析中にブラウザが使用するスタック
     t: HtmlTokenizer,                       // t.next() メソッドを使用する
+    xhtml: bool,                            // XHTML として解析するかどうか
 }
 
 impl HtmlParser {
     pub fn new(t: HtmlTokenizer) -> Self {
         Self {
-            window: Rc::new(RefCell::new(Window::new())),
+            window: Window::new(),
             mode: InsertionMode::Initial,
             original_insertion_mode: InsertionMode::Initial,
             stack_of_open_elements: Vec::new(),
             t,
+            xhtml: false,
         }
     }
 
+    // XHTML では空要素以外の自己終了タグも要素を閉じる
+    pub fn set_xhtml(&mut self, xhtml: bool) {
+        self.xhtml = xhtml;
+    }
+
+    // 作成したノードには、所属するウィンドウへの弱参照を持たせる
+    fn create_node(&self, kind: NodeKind) -> Node {
+        let mut node = Node::new(kind);
+        node.set_window(Rc::downgrade(&self.window));
+        node
+    }
+
     fn create_element(&self, tag: &str, attributes: Vec<Attribute>) -> Node {
-        Node::new(NodeKind::Element(Element::new(tag, attributes)))
+        self.create_node(NodeKind::Element(Element::new(tag, attributes)))
     }
 
     fn create_char(&self, c: char) -> Node {
         let mut s = String::new();
         s.push(c);
-        Node::new(NodeKind::Text(s))
+        self.create_node(NodeKind::Text(s))
     }
 
     // HTML の構造を解析して要素ノードを作成し、挿入先の位置を決定する
@@ -65,30 +79,24 @@ impl HtmlParser {
 
         // 参照しているノードに子ノードがある場合（ node から見て兄弟ノードがあるかどうか）
         if current.borrow().first_child().is_some() {
-            let mut last_sibling = current.borrow().first_child();
+            let mut last_sibling = current
+                .borrow()
+                .first_child()
+                .expect("failed to get a first child");
             loop {
-                last_sibling = match last_sibling {
-                    Some(ref node) => {
-                        if node.borrow().next_sibling().is_some() {
-                            node.borrow().next_sibling()
-                        } else {
-                            break;
-                        }
-                    }
-                    None => unimplemented!("last_sibling should be Some"),
+                let next = last_sibling.borrow().next_sibling();
+                match next {
+                    Some(n) => last_sibling = n,
+                    None => break,
                 }
             }
 
             last_sibling
-                .unwrap()
                 .borrow_mut()
                 .set_next_sibling(Some(node.clone()));
-            node.borrow_mut().set_previous_sibling(Rc::downgrade(
-                &current
-                    .borrow()
-                    .first_child()
-                    .expect("failed to get a first child"),
-            ));
+            // 前の兄弟ノードは最初の子ノードではなく、最後の兄弟ノード
+            node.borrow_mut()
+                .set_previous_sibling(Rc::downgrade(&last_sibling));
         } else {
             current.borrow_mut().set_first_child(Some(node.clone()));
         }
@@ -106,39 +114,76 @@ impl HtmlParser {
             None => return,
         };
 
-        // current がテキストノードの場合 → テキストノードに文字を追加
-        if let NodeKind::Text(ref mut s) = current.borrow_mut().kind {
-            s.push(c);
-            return;
-        };
+        // 最後の子ノードがテキストノードの場合 → テキストノードに文字を追加
+        let last_child = current.borrow().last_child();
+        if let Some(ref last) = last_child {
+            if let NodeKind::Text(ref mut s) = last.borrow_mut().kind {
+                s.push(c);
+                return;
+            }
+        }
 
-        if c == '\n' || c == ' ' {
+        // テキストノードの先頭の空白は無視するので、空白だけのテキストノードは作られない
+        if is_html_whitespace(c) {
             return;
         }
 
         let node = Rc::new(RefCell::new(self.create_char(c)));
 
-        if current.borrow().first_child().is_some() {
-            current
-                .borrow()
-                .first_child()
-                .unwrap()
-                .borrow_mut()
-                .set_next_sibling(Some(node.clone()));
-            node.borrow_mut().set_previous_sibling(Rc::downgrade(
-                &current
-                    .borrow()
-                    .first_child()
-                    .expect("failed to get a first child"),
-            ));
-        } else {
-            current.borrow_mut().set_first_child(Some(node.clone()));
+        match last_child {
+            Some(last) => {
+                last.borrow_mut().set_next_sibling(Some(node.clone()));
+                node.borrow_mut().set_previous_sibling(Rc::downgrade(&last));
+            }
+            None => current.borrow_mut().set_first_child(Some(node.clone())),
         }
 
         current.borrow_mut().set_last_child(Rc::downgrade(&node));
         node.borrow_mut().set_parent(Rc::downgrade(&current));
 
-        self.stack_of_open_elements.push(node);
+        // テキストノードは子を持たないので、オープン要素スタックには追加しない
+    }
+
+    // 開いている p 要素があれば、その要素までをスタックから取り出す
+    fn close_p_element(&mut self) {
+        if self.contain_in_stack(ElementKind::P) {
+            self.pop_until(ElementKind::P);
+        }
+    }
+
+    // 同じリストの中で開いている li 要素を閉じる
+    // スタックを上から見ていき、ul / ol に到達したらそれより外側の li は閉じない
+    fn close_list_item(&mut self) {
+        for node in self.stack_of_open_elements.iter().rev() {
+            match node.borrow().get_element_kind() {
+                Some(ElementKind::Li) => break,
+                Some(ElementKind::Ul) | Some(ElementKind::Ol) => return,
+                _ => {}
+            }
+        }
+        if self.contain_in_stack(ElementKind::Li) {
+            self.pop_until(ElementKind::Li);
+        }
+    }
+
+    // 2つ目の html や body の開始タグは新しい要素を作らず、
+    // 既存の要素がまだ持っていない属性だけを追加する
+    fn merge_attributes(&mut self, element_kind: ElementKind, attributes: Vec<Attribute>) {
+        let node = self
+            .stack_of_open_elements
+            .iter()
+            .find(|n| n.borrow().get_element_kind() == Some(element_kind));
+        if let Some(node) = node {
+            if let Some(element) = node.borrow_mut().get_element_mut() {
+                element.merge_attributes(attributes);
+            }
+        }
+    }
+
+    // 終了タグを持たない要素を追加し、すぐにスタックから取り出す
+    fn insert_void_element(&mut self, tag: &str, attributes: Vec<Attribute>) {
+        self.insert_element(tag, attributes);
+        self.stack_of_open_elements.pop();
     }
 
     fn pop_current_node(&mut self, element: ElementKind) -> bool {
@@ -155,12 +200,12 @@ impl HtmlParser {
         false
     }
 
+    // element_kind の要素が取り出されるまでスタックから取り出す
+    // スタックにその要素がなければ、パースエラーとして何もしない
     fn pop_until(&mut self, element_kind: ElementKind) {
-        assert!(
-            self.contain_in_stack(element_kind),
-            "stack doesn't have an element {:?}",
-            element_kind
-        );
+        if !self.contain_in_stack(element_kind) {
+            return;
+        }
 
         loop {
             let current = match self.stack_of_open_elements.pop() {
@@ -174,6 +219,32 @@ impl HtmlParser {
         }
     }
 
+    // 未対応のタグ名は ElementKind で区別できないので、タグ名で比較する
+    fn pop_until_tag_name(&mut self, tag: &str) {
+        loop {
+            let current = match self.stack_of_open_elements.pop() {
+                Some(n) => n,
+                None => return,
+            };
+
+            let matched = current
+                .borrow()
+                .get_element()
+                .is_some_and(|e| e.tag_name() == tag);
+            if matched {
+                return;
+            }
+        }
+    }
+
+    fn contain_tag_name_in_stack(&self, tag: &str) -> bool {
+        self.stack_of_open_elements.iter().any(|n| {
+            n.borrow()
+                .get_element()
+                .is_some_and(|e| e.tag_name() == tag)
+        })
+    }
+
     fn contain_in_stack(&mut self, element_kind: ElementKind) -> bool {
         for i in 0..self.stack_of_open_elements.len() {
             if self.stack_of_open_elements[i].borrow().get_element_kind() == Some(element_kind) {
@@ -188,8 +259,21 @@ impl HtmlParser {
         let mut token = self.t.next();
 
         while token.is_some() {
+            // コメントは DOM ツリーに追加せずに読み飛ばす
+            if let Some(HTMLToken::Comment(_)) = token {
+                token = self.t.next();
+                continue;
+            }
+
             match self.mode {
                 InsertionMode::Initial => {
+                    // DOCTYPE 宣言を読み込んだら、html 要素の前の状態に移る
+                    if let Some(HTMLToken::Doctype { .. }) = token {
+                        self.mode = InsertionMode::BeforeHtml;
+                        token = self.t.next();
+                        continue;
+                    }
+
                     // 文字トークンは無視する
                     if let Some(HTMLToken::Char(_)) = token {
                         token = self.t.next();
@@ -209,6 +293,7 @@ impl HtmlParser {
                         }
                         Some(HTMLToken::StartTag {
                             ref tag,
+                            original_tag: _,
                             self_closing: _,
                             ref attributes,
                         }) => {
@@ -240,6 +325,7 @@ impl HtmlParser {
                         }
                         Some(HTMLToken::StartTag {
                             ref tag,
+                            original_tag: _,
                             self_closing: _,
                             ref attributes,
                         }) => {
@@ -271,10 +357,12 @@ impl HtmlParser {
                         }
                         Some(HTMLToken::StartTag {
                             ref tag,
+                            original_tag: _,
                             self_closing: _,
                             ref attributes,
                         }) => {
-                            if tag == "style" || tag == "script" {
+                            // title の中身はトークナイザが RCDATA として文字トークンにする
+                            if tag == "style" || tag == "script" || tag == "title" {
                                 self.insert_element(tag, attributes.to_vec());
                                 self.original_insertion_mode = self.mode;
                                 self.mode = InsertionMode::Text;
@@ -282,29 +370,49 @@ impl HtmlParser {
                                 continue;
                             }
 
-                            if tag == "body" {
-                                self.pop_until(ElementKind::Head);
-                                self.mode = InsertionMode::AfterHead;
+                            if tag == "meta" || tag == "link" || tag == "base" {
+                                self.insert_void_element(tag, attributes.to_vec());
+                                token = self.t.next();
                                 continue;
                             }
 
-                            if let Ok(_element_kind) = ElementKind::from_str(tag) {
+                            if tag == "body" {
                                 self.pop_until(ElementKind::Head);
                                 self.mode = InsertionMode::AfterHead;
                                 continue;
                             }
+
+                            // head に置けない要素は、未知のタグも含めて head を暗黙的に閉じてから
+                            // body の前の状態でもう一度処理する
+                            self.pop_until(ElementKind::Head);
+                            self.mode = InsertionMode::AfterHead;
+                            continue;
                         }
-                        Some(HTMLToken::EndTag { ref tag }) => {
+                        Some(HTMLToken::EndTag { ref tag, .. }) => {
                             if tag == "head" {
                                 self.mode = InsertionMode::AfterHead;
                                 token = self.t.next();
                                 self.pop_until(ElementKind::Head);
                                 continue;
                             }
+
+                            // head を暗黙的に閉じてから、body の前の状態でもう一度処理する
+                            if tag == "body" || tag == "html" || tag == "br" {
+                                self.pop_until(ElementKind::Head);
+                                self.mode = InsertionMode::AfterHead;
+                                continue;
+                            }
                         }
                         Some(HTMLToken::Eof) | None => {
                             return self.window.clone();
                         }
+                        // コメントはループの先頭で読み飛ばしている
+                        Some(HTMLToken::Comment(_)) => unreachable!(),
+                        // Initial 以外に現れた DOCTYPE 宣言は無視する
+                        Some(HTMLToken::Doctype { .. }) => {
+                            token = self.t.next();
+                            continue;
+                        }
                     }
                     token = self.t.next();
                     continue;
@@ -320,6 +428,7 @@ impl HtmlParser {
                         }
                         Some(HTMLToken::StartTag {
                             ref tag,
+                            original_tag: _,
                             self_closing: _,
                             ref attributes,
                         }) => {
@@ -343,15 +452,54 @@ impl HtmlParser {
                 InsertionMode::InBody => match token {
                     Some(HTMLToken::StartTag {
                         ref tag,
-                        self_closing: _,
+                        original_tag: _,
+                        self_closing,
                         ref attributes,
                     }) => match tag.as_str() {
+                        "html" => {
+                            self.merge_attributes(ElementKind::Html, attributes.to_vec());
+                            token = self.t.next();
+                            continue;
+                        }
+                        "body" => {
+                            self.merge_attributes(ElementKind::Body, attributes.to_vec());
+                            token = self.t.next();
+                            continue;
+                        }
+                        // XHTML ではすべての要素で自己終了タグが有効なので、空の要素として閉じる
+                        _ if self.xhtml && self_closing => {
+                            self.insert_void_element(tag, attributes.to_vec());
+                            token = self.t.next();
+                            continue;
+                        }
+                        // 新しい段落は開いている段落を暗黙的に閉じる
                         "p" => {
+                            self.close_p_element();
+                            self.insert_element(tag, attributes.to_vec());
+                            token = self.t.next();
+                            continue;
+                        }
+                        // 見出しは開いている段落を暗黙的に閉じる
+                        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                            self.close_p_element();
                             self.insert_element(tag, attributes.to_vec());
                             token = self.t.next();
                             continue;
                         }
-                        "h1" | "h2" => {
+                        "div" | "table" | "ul" | "ol" | "form" => {
+                            self.close_p_element();
+                            self.insert_element(tag, attributes.to_vec());
+                            token = self.t.next();
+                            continue;
+                        }
+                        // 新しい li は開いている li を暗黙的に閉じる
+                        "li" => {
+                            self.close_list_item();
+                            self.insert_element(tag, attributes.to_vec());
+                            token = self.t.next();
+                            continue;
+                        }
+                        "span" => {
                             self.insert_element(tag, attributes.to_vec());
                             token = self.t.next();
                             continue;
@@ -361,9 +509,38 @@ impl HtmlParser {
                             token = self.t.next();
                             continue;
                         }
+                        "button" | "label" | "select" => {
+                            self.insert_element(tag, attributes.to_vec());
+                            token = self.t.next();
+                            continue;
+                        }
+                        // 新しい option は開いている option を暗黙的に閉じる
+                        "option" => {
+                            self.pop_current_node(ElementKind::Option);
+                            self.insert_element(tag, attributes.to_vec());
+                            token = self.t.next();
+                            continue;
+                        }
+                        "code" | "pre" => {
+                            self.insert_element(tag, attributes.to_vec());
+                            token = self.t.next();
+                            continue;
+                        }
+                        // 終了タグを持たない要素なので、すぐにスタックから取り出す
+                        _ if VOID_ELEMENTS.contains(&tag.as_str()) => {
+                            self.insert_void_element(tag, attributes.to_vec());
+                            token = self.t.next();
+                            continue;
+                        }
+                        // カスタム要素などの未対応のタグは汎用の要素として追加する
+                        _ if ElementKind::from_str(tag).is_err() => {
+                            self.insert_element(tag, attributes.to_vec());
+                            token = self.t.next();
+                            continue;
+                        }
                         _ => token = self.t.next(),
                     },
-                    Some(HTMLToken::EndTag { ref tag }) => match tag.as_str() {
+                    Some(HTMLToken::EndTag { ref tag, .. }) => match tag.as_str() {
                         "body" => {
                             self.mode = InsertionMode::AfterBody;
                             token = self.t.next();
@@ -376,7 +553,7 @@ impl HtmlParser {
                         "html" => {
                             if self.pop_current_node(ElementKind::Body) {
                                 self.mode = InsertionMode::AfterBody;
-                                assert!(self.pop_current_node(ElementKind::Html));
+                                self.pop_current_node(ElementKind::Html);
                             } else {
                                 token = self.t.next();
                             }
@@ -389,11 +566,25 @@ impl HtmlParser {
                             self.pop_until(element_kind);
                             continue;
                         }
-                        "h1" | "h2" => {
+                        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
                             let element_kind = ElementKind::from_str(tag)
                                 .expect("failed to convert string to ElementKind");
                             token = self.t.next();
-                            self.pop_until(element_kind);
+                            // 対応する開始タグがない終了タグは無視する
+                            if self.contain_in_stack(element_kind) {
+                                self.pop_until(element_kind);
+                            }
+                            continue;
+                        }
+                        "div" | "span" | "table" | "ul" | "ol" | "li" | "form" | "button"
+                        | "label" | "select" | "option" => {
+                            let element_kind = ElementKind::from_str(tag)
+                                .expect("failed to convert string to ElementKind");
+                            token = self.t.next();
+                            // 対応する開始タグがない終了タグは無視する
+                            if self.contain_in_stack(element_kind) {
+                                self.pop_until(element_kind);
+                            }
                             continue;
                         }
                         "a" => {
@@ -403,6 +594,22 @@ impl HtmlParser {
                             self.pop_until(element_kind);
                             continue;
                         }
+                        "code" | "pre" => {
+                            let element_kind = ElementKind::from_str(tag)
+                                .expect("failed to convert string to ElementKind");
+                            token = self.t.next();
+                            self.pop_until(element_kind);
+                            continue;
+                        }
+                        _ if ElementKind::from_str(tag).is_err() => {
+                            let tag = tag.clone();
+                            token = self.t.next();
+                            // 対応する開始タグがない終了タグは無視する
+                            if self.contain_tag_name_in_stack(&tag) {
+                                self.pop_until_tag_name(&tag);
+                            }
+                            continue;
+                        }
                         _ => token = self.t.next(),
                     },
                     Some(HTMLToken::Char(c)) => {
@@ -413,6 +620,13 @@ impl HtmlParser {
                     Some(HTMLToken::Eof) | None => {
                         return self.window.clone();
                     }
+                    // コメントはループの先頭で読み飛ばしている
+                    Some(HTMLToken::Comment(_)) => unreachable!(),
+                    // Initial 以外に現れた DOCTYPE 宣言は無視する
+                    Some(HTMLToken::Doctype { .. }) => {
+                        token = self.t.next();
+                        continue;
+                    }
                 },
                 // style タグと script タグが開始した後
                 InsertionMode::Text => {
@@ -420,15 +634,11 @@ impl HtmlParser {
                         Some(HTMLToken::Eof) | None => {
                             return self.window.clone();
                         }
-                        Some(HTMLToken::EndTag { ref tag }) => {
-                            if tag == "style" {
-                                self.pop_until(ElementKind::Style);
-                                self.mode = self.original_insertion_mode;
-                                token = self.t.next();
-                                continue;
-                            }
-                            if tag == "script" {
-                                self.pop_until(ElementKind::Script);
+                        Some(HTMLToken::EndTag { ref tag, .. }) => {
+                            if tag == "style" || tag == "script" || tag == "title" {
+                                let element_kind = ElementKind::from_str(tag)
+                                    .expect("failed to convert string to ElementKind");
+                                self.pop_until(element_kind);
                                 self.mode = self.original_insertion_mode;
                                 token = self.t.next();
                                 continue;
@@ -452,7 +662,7 @@ impl HtmlParser {
                             token = self.t.next();
                             continue;
                         }
-                        Some(HTMLToken::EndTag { ref tag }) => {
+                        Some(HTMLToken::EndTag { ref tag, .. }) => {
                             if tag == "html" {
                                 self.mode = InsertionMode::AfterAfterBody;
                                 token = self.t.next();
@@ -494,6 +704,8 @@ impl HtmlParser {
 mod tests {
     use super::*;
     use crate::alloc::string::ToString;
+    use crate::renderer::dom::api::get_target_element_node;
+    use crate::renderer::dom::node::NodeIterator;
     use alloc::vec;
 
     #[test]
@@ -612,6 +824,401 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_body_text() {
+        let html = "<html><head></head><body>Hello</body></html>".to_string();
+        let t = HtmlTokenizer::new(html);
+        let window = HtmlParser::new(t).construct_tree();
+        let document = window.borrow().document();
+        let body = get_target_element_node(Some(document), ElementKind::Body)
+            .expect("failed to get a body element");
+
+        let text = body
+            .borrow()
+            .first_child()
+            .expect("failed to get a first child of body");
+        match text.borrow().kind() {
+            NodeKind::Text(s) => assert_eq!("Hello", s),
+            _ => panic!("expected a text node: {:?}", text),
+        };
+    }
+
+    #[test]
+    fn test_whitespace_only_text_is_suppressed() {
+        let html = "<html><head></head><body>\n\t\r <p>x</p></body></html>".to_string();
+        let t = HtmlTokenizer::new(html);
+        let window = HtmlParser::new(t).construct_tree();
+        let document = window.borrow().document();
+        let body = get_target_element_node(Some(document), ElementKind::Body)
+            .expect("failed to get a body element");
+
+        let p = body
+            .borrow()
+            .first_child()
+            .expect("failed to get a first child of body");
+        assert_eq!(Some(ElementKind::P), p.borrow().get_element_kind());
+    }
+
+    #[test]
+    fn test_void_element() {
+        let html = "<html><head><meta charset=\"utf-8\"></head><body><p>a<br>b</p><hr><p>c</p></body></html>".to_string();
+        let t = HtmlTokenizer::new(html);
+        let window = HtmlParser::new(t).construct_tree();
+        let document = window.borrow().document();
+
+        let meta = get_target_element_node(Some(document.clone()), ElementKind::Head)
+            .expect("failed to get a head element")
+            .borrow()
+            .first_child()
+            .expect("failed to get a first child of head");
+        assert_eq!(
+            Some("meta".to_string()),
+            meta.borrow().get_element().map(|e| e.tag_name())
+        );
+
+        let body = get_target_element_node(Some(document), ElementKind::Body)
+            .expect("failed to get a body element");
+        let p = body
+            .borrow()
+            .first_child()
+            .expect("failed to get a first child of body");
+        assert_eq!("<p>a<br>b</p>".to_string(), p.borrow().outer_html());
+
+        // hr の後の p は hr の子ではなく兄弟になる
+        let hr = p
+            .borrow()
+            .next_sibling()
+            .expect("failed to get a next sibling of p");
+        assert_eq!("<hr>".to_string(), hr.borrow().outer_html());
+        let p2 = hr
+            .borrow()
+            .next_sibling()
+            .expect("failed to get a next sibling of hr");
+        assert_eq!("<p>c</p>".to_string(), p2.borrow().outer_html());
+    }
+
+    #[test]
+    fn test_list() {
+        let html = "<html><head></head><body><ul><li>a<li>b</ul><ol><li>c</li></ol></body></html>"
+            .to_string();
+        let t = HtmlTokenizer::new(html);
+        let window = HtmlParser::new(t).construct_tree();
+        let document = window.borrow().document();
+        let body = get_target_element_node(Some(document), ElementKind::Body)
+            .expect("failed to get a body element");
+
+        let ul = body
+            .borrow()
+            .first_child()
+            .expect("failed to get a first child of body");
+        assert_eq!(
+            "<ul><li>a</li><li>b</li></ul>".to_string(),
+            ul.borrow().outer_html()
+        );
+
+        let ol = ul
+            .borrow()
+            .next_sibling()
+            .expect("failed to get a next sibling of ul");
+        assert_eq!("<ol><li>c</li></ol>".to_string(), ol.borrow().outer_html());
+    }
+
+    #[test]
+    fn test_nested_list() {
+        let html = "<html><head></head><body><ul><li>a<ul><li>b<li>c</ul><li>d</ul></body></html>"
+            .to_string();
+        let t = HtmlTokenizer::new(html);
+        let window = HtmlParser::new(t).construct_tree();
+        let document = window.borrow().document();
+        let body = get_target_element_node(Some(document), ElementKind::Body)
+            .expect("failed to get a body element");
+
+        // 内側のリストの li は外側の li を閉じない
+        assert_eq!(
+            "<ul><li>a<ul><li>b</li><li>c</li></ul></li><li>d</li></ul>".to_string(),
+            body.borrow().inner_html()
+        );
+    }
+
+    #[test]
+    fn test_nested_div() {
+        let html = "<html><head></head><body><div><div>x</div></div><span>y</span></body></html>"
+            .to_string();
+        let t = HtmlTokenizer::new(html);
+        let window = HtmlParser::new(t).construct_tree();
+        let document = window.borrow().document();
+        let body = get_target_element_node(Some(document), ElementKind::Body)
+            .expect("failed to get a body element");
+
+        let outer = body
+            .borrow()
+            .first_child()
+            .expect("failed to get a first child of body");
+        assert_eq!(Some(ElementKind::Div), outer.borrow().get_element_kind());
+        let inner = outer
+            .borrow()
+            .first_child()
+            .expect("failed to get a first child of div");
+        assert_eq!(Some(ElementKind::Div), inner.borrow().get_element_kind());
+
+        let span = outer
+            .borrow()
+            .next_sibling()
+            .expect("failed to get a next sibling of div");
+        assert_eq!(Some(ElementKind::Span), span.borrow().get_element_kind());
+    }
+
+    #[test]
+    fn test_stray_end_tag() {
+        let html = "<html><head></head><body></div><p>x</p></span></body></html>".to_string();
+        let t = HtmlTokenizer::new(html);
+        let window = HtmlParser::new(t).construct_tree();
+        let document = window.borrow().document();
+        let body = get_target_element_node(Some(document), ElementKind::Body)
+            .expect("failed to get a body element");
+
+        assert_eq!("<p>x</p>".to_string(), body.borrow().inner_html());
+    }
+
+    #[test]
+    fn test_implicitly_closed_paragraph() {
+        let html = "<html><head></head><body><p>a<p>b</body></html>".to_string();
+        let t = HtmlTokenizer::new(html);
+        let window = HtmlParser::new(t).construct_tree();
+        let document = window.borrow().document();
+        let body = get_target_element_node(Some(document), ElementKind::Body)
+            .expect("failed to get a body element");
+
+        assert_eq!("<p>a</p><p>b</p>".to_string(), body.borrow().inner_html());
+    }
+
+    #[test]
+    fn test_title() {
+        let html = "<html><head><title>Hi</title></head><body></body></html>".to_string();
+        let t = HtmlTokenizer::new(html);
+        let window = HtmlParser::new(t).construct_tree();
+        let document = window.borrow().document();
+        let head = get_target_element_node(Some(document), ElementKind::Head)
+            .expect("failed to get a head element");
+
+        assert_eq!("<title>Hi</title>".to_string(), head.borrow().inner_html());
+    }
+
+    #[test]
+    fn test_sibling_links() {
+        let html = "<html><head></head><body><p>a</p><p>b</p><p>c</p></body></html>".to_string();
+        let t = HtmlTokenizer::new(html);
+        let window = HtmlParser::new(t).construct_tree();
+        let document = window.borrow().document();
+        let body = get_target_element_node(Some(document), ElementKind::Body)
+            .expect("failed to get a body element");
+
+        // next_sibling で最後までたどる
+        let mut forward = Vec::new();
+        let mut node = body.borrow().first_child();
+        while let Some(n) = node {
+            forward.push(n.borrow().text_content());
+            node = n.borrow().next_sibling();
+        }
+        assert_eq!(vec!["a", "b", "c"], forward);
+
+        // previous_sibling で最初までたどる
+        let mut backward = Vec::new();
+        let mut node = body.borrow().last_child();
+        while let Some(n) = node {
+            backward.push(n.borrow().text_content());
+            node = n.borrow().previous_sibling().upgrade();
+        }
+        assert_eq!(vec!["c", "b", "a"], backward);
+    }
+
+    #[test]
+    fn test_self_closing_in_xhtml() {
+        let html = "<html><head></head><body><div/>x</body></html>".to_string();
+
+        let mut parser = HtmlParser::new(HtmlTokenizer::new(html.clone()));
+        parser.set_xhtml(true);
+        let document = parser.construct_tree().borrow().document();
+        let body = get_target_element_node(Some(document), ElementKind::Body)
+            .expect("failed to get a body element");
+        assert_eq!("<div></div>x".to_string(), body.borrow().inner_html());
+
+        // HTML では空要素以外の自己終了タグは無視される
+        let document = HtmlParser::new(HtmlTokenizer::new(html))
+            .construct_tree()
+            .borrow()
+            .document();
+        let body = get_target_element_node(Some(document), ElementKind::Body)
+            .expect("failed to get a body element");
+        assert_eq!("<div>x</div>".to_string(), body.borrow().inner_html());
+    }
+
+    #[test]
+    fn test_doctype() {
+        let html = "<!DOCTYPE html><html><head></head><body></body></html>".to_string();
+        let t = HtmlTokenizer::new(html);
+        let window = HtmlParser::new(t).construct_tree();
+        let document = window.borrow().document();
+
+        let html = document
+            .borrow()
+            .first_child()
+            .expect("failed to get a first child of document");
+        assert_eq!(
+            Rc::new(RefCell::new(Node::new(NodeKind::Element(Element::new(
+                "html",
+                Vec::new()
+            ))))),
+            html
+        );
+        assert!(html.borrow().next_sibling().is_none());
+    }
+
+    #[test]
+    fn test_custom_element() {
+        let html = "<html><head></head><body><my-widget>hi</my-widget></body></html>".to_string();
+        let t = HtmlTokenizer::new(html);
+        let window = HtmlParser::new(t).construct_tree();
+        let document = window.borrow().document();
+
+        let widget = document
+            .borrow()
+            .first_child()
+            .expect("failed to get a first child of document")
+            .borrow()
+            .first_child()
+            .expect("failed to get a first child of html")
+            .borrow()
+            .next_sibling()
+            .expect("failed to get a next sibling of head")
+            .borrow()
+            .first_child()
+            .expect("failed to get a first child of body");
+
+        let element = widget
+            .borrow()
+            .get_element()
+            .expect("failed to get an element");
+        assert_eq!(ElementKind::Unknown, element.kind());
+        assert_eq!("my-widget", element.tag_name());
+
+        let text = widget
+            .borrow()
+            .first_child()
+            .expect("failed to get a first child of my-widget");
+        assert_eq!(NodeKind::Text("hi".to_string()), text.borrow().kind());
+    }
+
+    #[test]
+    fn test_custom_element_without_body() {
+        for html in [
+            "<my-widget>hi</my-widget>",
+            "<html><my-widget>hi</my-widget></html>",
+        ] {
+            let window = HtmlParser::new(HtmlTokenizer::new(html.to_string())).construct_tree();
+            let document = window.borrow().document();
+            assert_eq!(
+                "<html><head></head><body><my-widget>hi</my-widget></body></html>".to_string(),
+                document
+                    .borrow()
+                    .first_child()
+                    .expect("html should exist")
+                    .borrow()
+                    .outer_html()
+            );
+        }
+    }
+
+    #[test]
+    fn test_comment_is_ignored() {
+        let html = "<html><head></head><body><!-- note --><p>text</p></body></html>".to_string();
+        let t = HtmlTokenizer::new(html);
+        let window = HtmlParser::new(t).construct_tree();
+        let document = window.borrow().document();
+
+        let p = document
+            .borrow()
+            .first_child()
+            .expect("failed to get a first child of document")
+            .borrow()
+            .first_child()
+            .expect("failed to get a first child of html")
+            .borrow()
+            .next_sibling()
+            .expect("failed to get a next sibling of head")
+            .borrow()
+            .first_child()
+            .expect("failed to get a first child of body");
+
+        assert_eq!(
+            Rc::new(RefCell::new(Node::new(NodeKind::Element(Element::new(
+                "p",
+                Vec::new()
+            ))))),
+            p
+        );
+    }
+
+    #[test]
+    fn test_heading() {
+        let html = "<html><head></head><body><h1>Title</h1><h3>Sub</h3></body></html>".to_string();
+        let t = HtmlTokenizer::new(html);
+        let window = HtmlParser::new(t).construct_tree();
+        let document = window.borrow().document();
+        let body = get_target_element_node(Some(document), ElementKind::Body)
+            .expect("failed to get a body element");
+
+        let h1 = body
+            .borrow()
+            .first_child()
+            .expect("failed to get a first child of body");
+        assert_eq!(
+            Rc::new(RefCell::new(Node::new(NodeKind::Element(Element::new(
+                "h1",
+                Vec::new()
+            ))))),
+            h1
+        );
+
+        let text = h1
+            .borrow()
+            .first_child()
+            .expect("failed to get a first child of h1");
+        match text.borrow().kind() {
+            NodeKind::Text(s) => assert_eq!("Title", s),
+            _ => panic!("expected a text node: {:?}", text),
+        }
+
+        let h3 = h1
+            .borrow()
+            .next_sibling()
+            .expect("failed to get a next sibling of h1");
+        assert_eq!(Some(ElementKind::H3), h3.borrow().get_element_kind());
+    }
+
+    #[test]
+    fn test_heading_closes_paragraph() {
+        let html = "<html><head></head><body><p>text<h2>Title</h2></body></html>".to_string();
+        let t = HtmlTokenizer::new(html);
+        let window = HtmlParser::new(t).construct_tree();
+        let document = window.borrow().document();
+        let body = get_target_element_node(Some(document), ElementKind::Body)
+            .expect("failed to get a body element");
+
+        let p = body
+            .borrow()
+            .first_child()
+            .expect("failed to get a first child of body");
+        assert_eq!(Some(ElementKind::P), p.borrow().get_element_kind());
+
+        // h2 は p の子ではなく、兄弟として追加される
+        let h2 = p
+            .borrow()
+            .next_sibling()
+            .expect("failed to get a next sibling of p");
+        assert_eq!(Some(ElementKind::H2), h2.borrow().get_element_kind());
+    }
+
     #[test]
     fn test_multiple_nodes() {
         let html = "<html><head></head><body><p><a foo=bar>text</a></p></body></html>".to_string();
@@ -681,4 +1288,113 @@ mod tests {
             text
         );
     }
+
+    #[test]
+    fn test_repeated_body_merges_attributes() {
+        let html = "<html><head></head><body class=\"a\"><body id=\"b\" class=\"c\"><p>x</p></body></html>".to_string();
+        let window = HtmlParser::new(HtmlTokenizer::new(html)).construct_tree();
+        let document = window.borrow().document();
+        let body = get_target_element_node(Some(document.clone()), ElementKind::Body)
+            .expect("body should exist");
+
+        assert_eq!(
+            "<body class=\"a\" id=\"b\"><p>x</p></body>".to_string(),
+            body.borrow().outer_html()
+        );
+        let bodies = NodeIterator::new(document)
+            .filter(|n| n.borrow().get_element_kind() == Some(ElementKind::Body))
+            .count();
+        assert_eq!(1, bodies);
+    }
+
+    #[test]
+    fn test_repeated_html_merges_attributes() {
+        let html = "<html lang=\"en\"><head></head><body><html class=\"x\" lang=\"fr\"><p>x</p></body></html>".to_string();
+        let window = HtmlParser::new(HtmlTokenizer::new(html)).construct_tree();
+        let document = window.borrow().document();
+        let root = document.borrow().first_child().expect("html should exist");
+
+        assert_eq!(
+            "<html lang=\"en\" class=\"x\"><head></head><body><p>x</p></body></html>".to_string(),
+            root.borrow().outer_html()
+        );
+        let roots = NodeIterator::new(document)
+            .filter(|n| n.borrow().get_element_kind() == Some(ElementKind::Html))
+            .count();
+        assert_eq!(1, roots);
+    }
+
+    #[test]
+    fn test_only_end_tags() {
+        let html = "</body></html>".to_string();
+        let window = HtmlParser::new(HtmlTokenizer::new(html)).construct_tree();
+        let document = window.borrow().document();
+
+        let body = get_target_element_node(Some(document.clone()), ElementKind::Body)
+            .expect("body should exist");
+        assert!(body.borrow().first_child().is_none());
+        assert_eq!(
+            "<html><head></head><body></body></html>".to_string(),
+            document
+                .borrow()
+                .first_child()
+                .expect("html should exist")
+                .borrow()
+                .outer_html()
+        );
+    }
+
+    #[test]
+    fn test_unmatched_end_tags() {
+        let html = "<html><body></p></a></code>text</body></html>".to_string();
+        let window = HtmlParser::new(HtmlTokenizer::new(html)).construct_tree();
+        let body = get_target_element_node(Some(window.borrow().document()), ElementKind::Body)
+            .expect("body should exist");
+        assert_eq!("text".to_string(), body.borrow().text_content());
+    }
+
+    #[test]
+    fn test_form() {
+        let html = r#"<html><body><form action="/search" method="POST"><label>q</label><select><option>a<option>b</select><button>go</button></form></body></html>"#.to_string();
+        let window = HtmlParser::new(HtmlTokenizer::new(html)).construct_tree();
+        let body = get_target_element_node(Some(window.borrow().document()), ElementKind::Body)
+            .expect("body should exist");
+
+        let form = body.borrow().first_child().expect("form should exist");
+        assert_eq!(Some(ElementKind::Form), form.borrow().get_element_kind());
+        let element = form
+            .borrow()
+            .get_element()
+            .expect("form should be an element");
+        assert_eq!(Some("/search".to_string()), element.form_action());
+        assert_eq!(Some("post".to_string()), element.form_method());
+
+        let label = form.borrow().first_child().expect("label should exist");
+        assert_eq!(Some(ElementKind::Label), label.borrow().get_element_kind());
+        let select = label.borrow().next_sibling().expect("select should exist");
+        assert_eq!(
+            Some(ElementKind::Select),
+            select.borrow().get_element_kind()
+        );
+        let button = select.borrow().next_sibling().expect("button should exist");
+        assert_eq!(
+            Some(ElementKind::Button),
+            button.borrow().get_element_kind()
+        );
+        assert!(button.borrow().next_sibling().is_none());
+
+        // 2つ目の option は1つ目の option の兄弟になる
+        let first = select.borrow().first_child().expect("option should exist");
+        assert_eq!(Some(ElementKind::Option), first.borrow().get_element_kind());
+        let second = first
+            .borrow()
+            .next_sibling()
+            .expect("second option should exist");
+        assert_eq!(
+            Some(ElementKind::Option),
+            second.borrow().get_element_kind()
+        );
+        assert_eq!("a".to_string(), first.borrow().text_content());
+        assert_eq!("b".to_string(), second.borrow().text_content());
+    }
 }
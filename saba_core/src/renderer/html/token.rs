@@ -1,21 +1,42 @@
+use crate::error::Error;
 use crate::renderer::html::attribute::Attribute;
+use alloc::format;
 use alloc::string::String;
 use alloc::vec::Vec;
 
+// 入力の終端を読んだときに返す文字。終端かどうかは is_eof で判定する
+const EOF_CHAR: char = '\0';
+
+// 入力に含まれる NULL 文字の代わりに使う U+FFFD REPLACEMENT CHARACTER
+const REPLACEMENT_CHAR: char = '\u{FFFD}';
+
+// 対応する終了タグが現れるまで、中身を文字として扱う要素
+static RAW_TEXT_ELEMENTS: [&str; 4] = ["script", "style", "title", "textarea"];
+
+// 同じ位置の文字を再消費し続けられる上限回数。これを超えたら無限ループとみなす
+const MAX_RECONSUME_COUNT: usize = 64;
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum HTMLToken {
     // タグの開始
     StartTag {
-        tag: String,
+        tag: String,          // 小文字に正規化したタグ名
+        original_tag: String, // 入力に書かれていたままのタグ名
         self_closing: bool,
         attributes: Vec<Attribute>,
     },
     // タグの終了
     EndTag {
         tag: String,
+        original_tag: String,
+    },
+    Char(char),      // 文字
+    Comment(String), // コメント（<!-- と --> の間の文字列）
+    // DOCTYPE 宣言
+    Doctype {
+        name: Option<String>,
     },
-    Char(char), // 文字
-    Eof,        // 入力文字列の終了
+    Eof, // 入力文字列の終了
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -33,10 +54,20 @@ pub enum State {
     AttributeValueUnquoted,
     AfterAttributeValueQuoted,
     SelfClosingStartTag,
-    ScriptData,
-    ScriptDataLessThanSign,
-    ScriptDataEndTagOpen,
-    ScriptDataEndTagName,
+    MarkupDeclarationOpen,
+    CommentStart,
+    Comment,
+    CommentEndDash,
+    CommentEnd,
+    Doctype,
+    BeforeDoctypeName,
+    DoctypeName,
+    AfterDoctypeName,
+    CdataSection,
+    RawText,
+    RawTextLessThanSign,
+    RawTextEndTagOpen,
+    RawTextEndTagName,
     TemporaryBuffer,
 }
 
@@ -48,6 +79,10 @@ pub struct HtmlTokenizer {
     latest_token: Option<HTMLToken>,
     input: Vec<char>,
     buf: String,
+    raw_text_tag: String,   // RawText 状態を抜けるための終了タグの名前
+    reconsume_count: usize, // 位置を進めずに再消費した回数
+    errors: Vec<Error>,
+    peeked: Option<Option<HTMLToken>>, // peek で先読みしたトークン
 }
 
 // 文字列からトークンに変換する構造体
@@ -60,22 +95,66 @@ impl HtmlTokenizer {
             latest_token: None,
             input: html.chars().collect(), // HTML 文字列を input として格納
             buf: String::new(),
+            raw_text_tag: String::new(),
+            reconsume_count: 0,
+            errors: Vec::new(),
+            peeked: None,
+        }
+    }
+
+    // 次のトークンを消費せずに返す
+    pub fn peek(&mut self) -> Option<&HTMLToken> {
+        if self.peeked.is_none() {
+            let token = self.next();
+            self.peeked = Some(token);
+        }
+
+        match self.peeked {
+            Some(Some(ref token)) => Some(token),
+            _ => None,
         }
     }
 
+    // トークン化の途中で記録されたエラー
+    pub fn errors(&self) -> Vec<Error> {
+        self.errors.clone()
+    }
+
+    // 直前に消費したのが入力の終端を越えた位置かどうか
     fn is_eof(&self) -> bool {
         self.pos > self.input.len()
     }
 
+    // 入力の終端を越えて読んだ場合は EOF_CHAR を返し、pos は終端の1つ先で止める
     fn consume_next_input(&mut self) -> char {
-        let c = self.input[self.pos];
-        self.pos += 1;
+        let c = self.input.get(self.pos).copied().unwrap_or(EOF_CHAR);
+        if self.pos <= self.input.len() {
+            self.pos += 1;
+        }
         c
     }
 
     fn reconsume_input(&mut self) -> char {
         self.reconsume = false;
-        self.input[self.pos - 1]
+        self.input.get(self.pos - 1).copied().unwrap_or(EOF_CHAR)
+    }
+
+    // 直前に消費した文字から始まる入力が s と一致するかどうか
+    fn next_chars_are(&self, s: &str) -> bool {
+        let start = self.pos - 1;
+        s.chars()
+            .enumerate()
+            .all(|(i, c)| self.input.get(start + i) == Some(&c))
+    }
+
+    // 大文字と小文字を区別せずに next_chars_are と同じ判定をする
+    fn next_chars_are_ignore_case(&self, s: &str) -> bool {
+        let start = self.pos - 1;
+        s.chars().enumerate().all(|(i, c)| {
+            self.input
+                .get(start + i)
+                .is_some_and(|d| d.eq_ignore_ascii_case(&c))
+        })
     }
 
     // StartTag or EndTag を生成して、last_token に設定する
@@ -83,36 +162,102 @@ impl HtmlTokenizer {
         if start_tag_token {
             self.latest_token = Some(HTMLToken::StartTag {
                 tag: String::new(),
+                original_tag: String::new(),
                 self_closing: false,
                 attributes: Vec::new(),
             });
         } else {
-            self.latest_token = Some(HTMLToken::EndTag { tag: String::new() });
+            self.latest_token = Some(HTMLToken::EndTag {
+                tag: String::new(),
+                original_tag: String::new(),
+            });
         }
     }
 
+    // タグ名は小文字に正規化したものと、元の大文字・小文字を保ったものの両方を記録する
     fn append_tag_name(&mut self, c: char) {
         assert!(self.latest_token.is_some());
         if let Some(t) = self.latest_token.as_mut() {
             match t {
                 HTMLToken::StartTag {
                     ref mut tag,
+                    ref mut original_tag,
                     self_closing: _,
                     attributes: _,
                 }
-                | HTMLToken::EndTag { ref mut tag } => tag.push(c),
+                | HTMLToken::EndTag {
+                    ref mut tag,
+                    ref mut original_tag,
+                } => {
+                    tag.push(c.to_ascii_lowercase());
+                    original_tag.push(c);
+                }
                 _ => panic!("`latest_token` should be either StartTag or EndTag"),
             }
         }
     }
 
+    fn append_comment(&mut self, c: char) {
+        assert!(self.latest_token.is_some());
+
+        if let Some(t) = self.latest_token.as_mut() {
+            match t {
+                HTMLToken::Comment(ref mut data) => data.push(c),
+                _ => panic!("`latest_token` should be Comment"),
+            }
+        }
+    }
+
+    fn append_doctype_name(&mut self, c: char) {
+        assert!(self.latest_token.is_some());
+
+        if let Some(t) = self.latest_token.as_mut() {
+            match t {
+                HTMLToken::Doctype { ref mut name } => name.get_or_insert_with(String::new).push(c),
+                _ => panic!("`latest_token` should be Doctype"),
+            }
+        }
+    }
+
     fn take_latest_token(&mut self) -> Option<HTMLToken> {
         assert!(self.latest_token.is_some());
 
-        let t = self.latest_token.as_ref().cloned();
+        let mut t = self.latest_token.as_ref().cloned();
         self.latest_token = None;
         assert!(self.latest_token.is_none());
 
+        // 同じ名前の属性が複数ある場合は、最初の属性だけを残す
+        if let Some(HTMLToken::StartTag {
+            tag: _,
+            original_tag: _,
+            self_closing: _,
+            ref mut attributes,
+        }) = t
+        {
+            let mut names: Vec<String> = Vec::new();
+            attributes.retain(|attr| {
+                if names.contains(&attr.name()) {
+                    return false;
+                }
+                names.push(attr.name());
+                true
+            });
+        }
+
+        // 中身をタグとして解釈しない要素の開始タグであれば、RawText 状態に移る
+        if let Some(HTMLToken::StartTag {
+            ref tag,
+            original_tag: _,
+            self_closing: false,
+            attributes: _,
+        }) = t
+        {
+            if RAW_TEXT_ELEMENTS.contains(&tag.as_str()) {
+                self.raw_text_tag = tag.clone();
+                self.state = State::RawText;
+            }
+        }
+
         t
     }
 
@@ -123,6 +268,7 @@ impl HtmlTokenizer {
             match t {
                 HTMLToken::StartTag {
                     tag: _,
+                    original_tag: _,
                     self_closing: _,
                     ref mut attributes,
                 } => {
@@ -141,6 +287,7 @@ impl HtmlTokenizer {
             match t {
                 HTMLToken::StartTag {
                     tag: _,
+                    original_tag: _,
                     self_closing: _,
                     ref mut attributes,
                 } => {
@@ -162,6 +309,7 @@ impl HtmlTokenizer {
             match t {
                 HTMLToken::StartTag {
                     tag: _,
+                    original_tag: _,
                     ref mut self_closing,
                     attributes: _,
                 } => *self_closing = true,
@@ -171,20 +319,48 @@ impl HtmlTokenizer {
     }
 }
 
+// HTML の仕様で空白として扱う文字（スペース、タブ、改行、フォームフィード、復帰）
+pub fn is_html_whitespace(c: char) -> bool {
+    matches!(c, ' ' | '\t' | '\n' | '\x0C' | '\r')
+}
+
 // Iterator トレイトを HTMLTokenizer に対して実装
 impl Iterator for HtmlTokenizer {
     type Item = HTMLToken;
 
     // Iterator が返すのは HTMLToken か None
     fn next(&mut self) -> Option<Self::Item> {
-        if self.pos >= self.input.len() {
+        // peek で先読みしたトークンがあれば、それを返す
+        if let Some(token) = self.peeked.take() {
+            return token;
+        }
+
+        // 空の入力、または Eof トークンを返した後は何も返さない
+        // 終端の文字を再消費する場合は、まだ Eof トークンを返していない
+        if self.input.is_empty() || (self.is_eof() && !self.reconsume) {
             return None;
         }
 
         loop {
             let c = match self.reconsume {
-                true => self.reconsume_input(),
-                false => self.consume_next_input(),
+                true => {
+                    // デバッグビルドでは、ステートの実装ミスによる無限ループを検出して打ち切る
+                    self.reconsume_count += 1;
+                    if cfg!(debug_assertions) && self.reconsume_count > MAX_RECONSUME_COUNT {
+                        self.errors.push(Error::Other(format!(
+                            "reconsumed the input at {} more than {} times in {:?}",
+                            self.pos, MAX_RECONSUME_COUNT, self.state
+                        )));
+                        self.reconsume = false;
+                        self.pos = self.input.len() + 1;
+                        return Some(HTMLToken::Eof);
+                    }
+                    self.reconsume_input()
+                }
+                false => {
+                    self.reconsume_count = 0;
+                    self.consume_next_input()
+                }
             };
 
             match self.state {
@@ -197,6 +373,9 @@ impl Iterator for HtmlTokenizer {
                     if self.is_eof() {
                         return Some(HTMLToken::Eof);
                     }
+                    if c == '\0' {
+                        return Some(HTMLToken::Char(REPLACEMENT_CHAR));
+                    }
                     // それ以外の場合は、文字トークンを返す
                     return Some(HTMLToken::Char(c));
                 }
@@ -207,6 +386,11 @@ impl Iterator for HtmlTokenizer {
                         continue;
                     }
 
+                    if c == '!' {
+                        self.state = State::MarkupDeclarationOpen;
+                        continue;
+                    }
+
                     // アルファベットの場合、その文字を再度取り扱うため、
                     // reconsume を true に設定し、TagName 状態に移行
                     if c.is_ascii_alphabetic() {
@@ -217,8 +401,11 @@ impl Iterator for HtmlTokenizer {
                         continue;
                     }
 
+                    // 末尾の "<" は文字として扱い、次の呼び出しで Eof を返す
                     if self.is_eof() {
-                        return Some(HTMLToken::Eof);
+                        self.reconsume = true;
+                        self.state = State::Data;
+                        return Some(HTMLToken::Char('<'));
                     }
 
                     self.reconsume = true;
@@ -238,7 +425,7 @@ impl Iterator for HtmlTokenizer {
                     }
                 }
                 State::TagName => {
-                    if c == ' ' {
+                    if is_html_whitespace(c) {
                         self.state = State::BeforeAttributeName;
                         continue;
                     }
@@ -254,18 +441,22 @@ impl Iterator for HtmlTokenizer {
                         return self.take_latest_token();
                     }
 
-                    if c.is_ascii_uppercase() {
-                        self.append_tag_name(c.to_ascii_lowercase());
-                        continue;
-                    }
-
                     if self.is_eof() {
                         return Some(HTMLToken::Eof);
                     }
 
+                    if c == '\0' {
+                        self.append_tag_name(REPLACEMENT_CHAR);
+                        continue;
+                    }
+
                     self.append_tag_name(c);
                 }
                 State::BeforeAttributeName => {
+                    if is_html_whitespace(c) {
+                        continue;
+                    }
+
                     if c == '/' || c == '>' || self.is_eof() {
                         self.reconsume = true;
                         self.state = State::AfterAttributeName;
@@ -277,7 +468,7 @@ impl Iterator for HtmlTokenizer {
                     self.start_new_attribute();
                 }
                 State::AttributeName => {
-                    if c == ' ' || c == '/' || c == '>' || self.is_eof() {
+                    if is_html_whitespace(c) || c == '/' || c == '>' || self.is_eof() {
                         self.reconsume = true;
                         self.state = State::AfterAttributeName;
                         continue;
@@ -296,7 +487,7 @@ impl Iterator for HtmlTokenizer {
                     self.append_attribute(c, true)
                 }
                 State::AfterAttributeName => {
-                    if c == ' ' {
+                    if is_html_whitespace(c) {
                         continue;
                     }
 
@@ -324,7 +515,7 @@ impl Iterator for HtmlTokenizer {
                     self.start_new_attribute();
                 }
                 State::BeforeAttributeValue => {
-                    if c == ' ' {
+                    if is_html_whitespace(c) {
                         continue;
                     }
 
@@ -351,6 +542,11 @@ impl Iterator for HtmlTokenizer {
                         return Some(HTMLToken::Eof);
                     }
 
+                    if c == '\0' {
+                        self.append_attribute(REPLACEMENT_CHAR, false);
+                        continue;
+                    }
+
                     self.append_attribute(c, /*is_name*/ false);
                 }
                 State::AttributeValueSingleQuoted => {
@@ -363,10 +559,15 @@ impl Iterator for HtmlTokenizer {
                         return Some(HTMLToken::Eof);
                     }
 
+                    if c == '\0' {
+                        self.append_attribute(REPLACEMENT_CHAR, false);
+                        continue;
+                    }
+
                     self.append_attribute(c, /*is_name*/ false);
                 }
                 State::AttributeValueUnquoted => {
-                    if c == ' ' {
+                    if is_html_whitespace(c) {
                         self.state = State::BeforeAttributeName;
                         continue;
                     }
@@ -380,11 +581,16 @@ impl Iterator for HtmlTokenizer {
                         return Some(HTMLToken::Eof);
                     }
 
+                    if c == '\0' {
+                        self.append_attribute(REPLACEMENT_CHAR, false);
+                        continue;
+                    }
+
                     self.append_attribute(c, false);
                 }
                 // 属性の値を処理した後の状態
                 State::AfterAttributeValueQuoted => {
-                    if c == ' ' {
+                    if is_html_whitespace(c) {
                         self.state = State::BeforeAttributeName;
                         continue;
                     }
@@ -418,10 +624,172 @@ impl Iterator for HtmlTokenizer {
                         return Some(HTMLToken::Eof);
                     }
                 }
-                // <script> タグに書かれている JavaScript を処理する状態
-                State::ScriptData => {
+                // "<!" の後の状態
+                State::MarkupDeclarationOpen => {
+                    if self.next_chars_are("--") {
+                        self.pos += "--".len() - 1;
+                        self.latest_token = Some(HTMLToken::Comment(String::new()));
+                        self.state = State::CommentStart;
+                        continue;
+                    }
+
+                    if self.next_chars_are_ignore_case("DOCTYPE") {
+                        self.pos += "DOCTYPE".len() - 1;
+                        self.latest_token = Some(HTMLToken::Doctype { name: None });
+                        self.state = State::Doctype;
+                        continue;
+                    }
+
+                    // 本来は SVG や MathML などの外部コンテンツの中でのみ有効
+                    if self.next_chars_are("[CDATA[") {
+                        self.pos += "[CDATA[".len() - 1;
+                        self.state = State::CdataSection;
+                        continue;
+                    }
+
+                    if self.is_eof() {
+                        return Some(HTMLToken::Eof);
+                    }
+
+                    self.reconsume = true;
+                    self.state = State::Data;
+                }
+                State::CommentStart => {
+                    // "<!-->" のような空のコメント
+                    if c == '>' {
+                        self.state = State::Data;
+                        return self.take_latest_token();
+                    }
+
+                    self.reconsume = true;
+                    self.state = State::Comment;
+                }
+                State::Comment => {
+                    if c == '-' {
+                        self.state = State::CommentEndDash;
+                        continue;
+                    }
+
+                    if self.is_eof() {
+                        return Some(HTMLToken::Eof);
+                    }
+
+                    self.append_comment(c);
+                }
+                // コメント内で "-" が1つ現れた状態
+                State::CommentEndDash => {
+                    if c == '-' {
+                        self.state = State::CommentEnd;
+                        continue;
+                    }
+
+                    if self.is_eof() {
+                        return Some(HTMLToken::Eof);
+                    }
+
+                    // "-" の後に別の文字が続いた場合は、コメントの中身として扱う
+                    self.append_comment('-');
+                    self.reconsume = true;
+                    self.state = State::Comment;
+                }
+                // コメント内で "--" が現れた状態
+                State::CommentEnd => {
+                    if c == '>' {
+                        self.state = State::Data;
+                        return self.take_latest_token();
+                    }
+
+                    if c == '-' {
+                        self.append_comment('-');
+                        continue;
+                    }
+
+                    if self.is_eof() {
+                        return Some(HTMLToken::Eof);
+                    }
+
+                    self.append_comment('-');
+                    self.append_comment('-');
+                    self.reconsume = true;
+                    self.state = State::Comment;
+                }
+                State::Doctype => {
+                    if is_html_whitespace(c) {
+                        self.state = State::BeforeDoctypeName;
+                        continue;
+                    }
+
+                    if self.is_eof() {
+                        return Some(HTMLToken::Eof);
+                    }
+
+                    self.reconsume = true;
+                    self.state = State::BeforeDoctypeName;
+                }
+                State::BeforeDoctypeName => {
+                    if is_html_whitespace(c) {
+                        continue;
+                    }
+
+                    // "<!DOCTYPE>" のように名前がない場合
+                    if c == '>' {
+                        self.state = State::Data;
+                        return self.take_latest_token();
+                    }
+
+                    if self.is_eof() {
+                        return Some(HTMLToken::Eof);
+                    }
+
+                    self.append_doctype_name(c.to_ascii_lowercase());
+                    self.state = State::DoctypeName;
+                }
+                State::DoctypeName => {
+                    if is_html_whitespace(c) {
+                        self.state = State::AfterDoctypeName;
+                        continue;
+                    }
+
+                    if c == '>' {
+                        self.state = State::Data;
+                        return self.take_latest_token();
+                    }
+
+                    if self.is_eof() {
+                        return Some(HTMLToken::Eof);
+                    }
+
+                    self.append_doctype_name(c.to_ascii_lowercase());
+                }
+                // PUBLIC や SYSTEM などの識別子は読み飛ばす
+                State::AfterDoctypeName => {
+                    if c == '>' {
+                        self.state = State::Data;
+                        return self.take_latest_token();
+                    }
+
+                    if self.is_eof() {
+                        return Some(HTMLToken::Eof);
+                    }
+                }
+                // "]]>" が現れるまで、中身をすべて文字として扱う状態
+                State::CdataSection => {
+                    if c == ']' && self.next_chars_are("]]>") {
+                        self.pos += "]]>".len() - 1;
+                        self.state = State::Data;
+                        continue;
+                    }
+
+                    if self.is_eof() {
+                        return Some(HTMLToken::Eof);
+                    }
+
+                    return Some(HTMLToken::Char(c));
+                }
+                // <script> や <title> などの中身を、対応する終了タグが現れるまで文字として扱う状態
+                State::RawText => {
                     if c == '<' {
-                        self.state = State::ScriptDataLessThanSign;
+                        self.state = State::RawTextLessThanSign;
                         continue;
                     }
 
@@ -431,43 +799,50 @@ impl Iterator for HtmlTokenizer {
 
                     return Some(HTMLToken::Char(c));
                 }
-                // <script> 開始タグの中で小なり記号が出てきた時の状態
-                State::ScriptDataLessThanSign => {
+                // RawText の中で小なり記号が出てきた時の状態
+                State::RawTextLessThanSign => {
                     if c == '/' {
                         self.buf = String::new();
-                        self.state = State::ScriptDataEndTagOpen;
+                        self.state = State::RawTextEndTagOpen;
                         continue;
                     }
 
                     self.reconsume = true;
-                    self.state = State::ScriptData;
+                    self.state = State::RawText;
                     return Some(HTMLToken::Char('<'));
                 }
-                State::ScriptDataEndTagOpen => {
+                State::RawTextEndTagOpen => {
                     if c.is_ascii_alphabetic() {
                         self.reconsume = true;
-                        self.state = State::ScriptDataEndTagName;
+                        self.state = State::RawTextEndTagName;
                         self.create_tag(false);
                         continue;
                     }
 
                     self.reconsume = true;
-                    self.state = State::ScriptData;
+                    self.state = State::RawText;
                     return Some(HTMLToken::Char('<'));
                 }
-                // <script> の (script) を解析している状態
-                State::ScriptDataEndTagName => {
-                    if c == '>' {
+                // </script> の (script) の部分を解析している状態
+                State::RawTextEndTagName => {
+                    // 開始タグと同じ名前の終了タグの場合のみ RawText を抜ける
+                    if c == '>' && self.buf.to_ascii_lowercase() == self.raw_text_tag {
                         self.state = State::Data;
                         return self.take_latest_token();
                     }
 
                     if c.is_ascii_alphabetic() {
                         self.buf.push(c);
-                        self.append_tag_name(c.to_ascii_lowercase());
+                        self.append_tag_name(c);
                         continue;
                     }
 
+                    if self.is_eof() {
+                        return Some(HTMLToken::Eof);
+                    }
+
+                    // 終了タグではなかったので、読んだ文字をそのまま文字として返す
+                    self.latest_token = None;
                     self.state = State::TemporaryBuffer;
                     self.buf = String::from("</") + &self.buf;
                     self.buf.push(c);
@@ -477,7 +852,7 @@ impl Iterator for HtmlTokenizer {
                     self.reconsume = true;
 
                     if self.buf.chars().count() == 0 {
-                        self.state = State::ScriptData;
+                        self.state = State::RawText;
                         continue;
                     }
 
@@ -514,11 +889,13 @@ mod tests {
         let expected = [
             HTMLToken::StartTag {
                 tag: "body".to_string(),
+                original_tag: "body".to_string(),
                 self_closing: false,
                 attributes: Vec::new(),
             },
             HTMLToken::EndTag {
                 tag: "body".to_string(),
+                original_tag: "body".to_string(),
             },
         ];
 
@@ -556,11 +933,13 @@ mod tests {
         let expected = [
             HTMLToken::StartTag {
                 tag: "p".to_string(),
+                original_tag: "p".to_string(),
                 self_closing: false,
                 attributes: vec![attr1, attr2, attr3],
             },
             HTMLToken::EndTag {
                 tag: "p".to_string(),
+                original_tag: "p".to_string(),
             },
         ];
 
@@ -575,6 +954,7 @@ mod tests {
         let mut tokenizer = HtmlTokenizer::new(html);
         let expected = [HTMLToken::StartTag {
             tag: "img".to_string(),
+            original_tag: "img".to_string(),
             self_closing: true,
             attributes: Vec::new(),
         }];
@@ -591,6 +971,7 @@ mod tests {
         let expected = [
             HTMLToken::StartTag {
                 tag: "script".to_string(),
+                original_tag: "script".to_string(),
                 self_closing: false,
                 attributes: Vec::new(),
             },
@@ -603,6 +984,356 @@ mod tests {
             HTMLToken::Char('e'),
             HTMLToken::EndTag {
                 tag: "script".to_string(),
+                original_tag: "script".to_string(),
+            },
+        ];
+        for e in expected {
+            assert_eq!(Some(e), tokenizer.next());
+        }
+    }
+
+    #[test]
+    fn test_cdata_section() {
+        let html = "<![CDATA[a<b]]>c".to_string();
+        let mut tokenizer = HtmlTokenizer::new(html);
+        let expected = [
+            HTMLToken::Char('a'),
+            HTMLToken::Char('<'),
+            HTMLToken::Char('b'),
+        ];
+        for e in expected {
+            assert_eq!(Some(e), tokenizer.next());
+        }
+
+        // "]]>" の後はデータ状態に戻る
+        assert_eq!(Some(HTMLToken::Char('c')), tokenizer.next());
+        assert_eq!(State::Data, tokenizer.state);
+        assert_eq!(Some(HTMLToken::Eof), tokenizer.next());
+        assert!(tokenizer.next().is_none());
+    }
+
+    #[test]
+    fn test_comment() {
+        let html = "<!-- a-b --><p></p>".to_string();
+        let mut tokenizer = HtmlTokenizer::new(html);
+        let expected = [
+            HTMLToken::Comment(" a-b ".to_string()),
+            HTMLToken::StartTag {
+                tag: "p".to_string(),
+                original_tag: "p".to_string(),
+                self_closing: false,
+                attributes: Vec::new(),
+            },
+            HTMLToken::EndTag {
+                tag: "p".to_string(),
+                original_tag: "p".to_string(),
+            },
+        ];
+        for e in expected {
+            assert_eq!(Some(e), tokenizer.next());
+        }
+    }
+
+    #[test]
+    fn test_comment_with_dashes() {
+        let html = "<!--a--b---->".to_string();
+        let mut tokenizer = HtmlTokenizer::new(html);
+        assert_eq!(
+            Some(HTMLToken::Comment("a--b--".to_string())),
+            tokenizer.next()
+        );
+    }
+
+    #[test]
+    fn test_doctype() {
+        let html = "<!DOCTYPE html><html></html>".to_string();
+        let mut tokenizer = HtmlTokenizer::new(html);
+        let expected = [
+            HTMLToken::Doctype {
+                name: Some("html".to_string()),
+            },
+            HTMLToken::StartTag {
+                tag: "html".to_string(),
+                original_tag: "html".to_string(),
+                self_closing: false,
+                attributes: Vec::new(),
+            },
+            HTMLToken::EndTag {
+                tag: "html".to_string(),
+                original_tag: "html".to_string(),
+            },
+        ];
+        for e in expected {
+            assert_eq!(Some(e), tokenizer.next());
+        }
+    }
+
+    #[test]
+    fn test_doctype_case_insensitive() {
+        let html = "<!doctype HTML>".to_string();
+        let mut tokenizer = HtmlTokenizer::new(html);
+        assert_eq!(
+            Some(HTMLToken::Doctype {
+                name: Some("html".to_string()),
+            }),
+            tokenizer.next()
+        );
+    }
+
+    #[test]
+    fn test_eof_after_last_token() {
+        let html = "<p>".to_string();
+        let mut tokenizer = HtmlTokenizer::new(html);
+        assert_eq!(
+            Some(HTMLToken::StartTag {
+                tag: "p".to_string(),
+                original_tag: "p".to_string(),
+                self_closing: false,
+                attributes: Vec::new(),
+            }),
+            tokenizer.next()
+        );
+        assert_eq!(Some(HTMLToken::Eof), tokenizer.next());
+        assert_eq!(None, tokenizer.next());
+    }
+
+    #[test]
+    fn test_unterminated_tag() {
+        let mut tokenizer = HtmlTokenizer::new("<div".to_string());
+        assert_eq!(Some(HTMLToken::Eof), tokenizer.next());
+        assert_eq!(None, tokenizer.next());
+    }
+
+    #[test]
+    fn test_unterminated_attribute() {
+        let mut tokenizer = HtmlTokenizer::new("<div class=".to_string());
+        assert_eq!(Some(HTMLToken::Eof), tokenizer.next());
+        assert_eq!(None, tokenizer.next());
+    }
+
+    #[test]
+    fn test_trailing_less_than_sign() {
+        let mut tokenizer = HtmlTokenizer::new("a<".to_string());
+        assert_eq!(Some(HTMLToken::Char('a')), tokenizer.next());
+        assert_eq!(Some(HTMLToken::Char('<')), tokenizer.next());
+        assert_eq!(Some(HTMLToken::Eof), tokenizer.next());
+        assert_eq!(None, tokenizer.next());
+    }
+
+    #[test]
+    fn test_pathological_input_terminates() {
+        let html = "<<!<a =<b/<c '<!-".repeat(100);
+        let mut tokenizer = HtmlTokenizer::new(html);
+        let mut count = 0;
+        for _ in tokenizer.by_ref() {
+            count += 1;
+            assert!(count < 10000, "tokenizer doesn't terminate");
+        }
+        assert!(tokenizer.errors().is_empty());
+    }
+
+    // 検出はデバッグビルドだけで行う
+    #[test]
+    #[cfg(debug_assertions)]
+    fn test_reconsume_loop_is_detected() {
+        let mut tokenizer = HtmlTokenizer::new("abc".to_string());
+        assert_eq!(Some(HTMLToken::Char('a')), tokenizer.next());
+
+        // 同じ位置で再消費を繰り返している状態を作る
+        tokenizer.reconsume = true;
+        tokenizer.reconsume_count = MAX_RECONSUME_COUNT;
+        assert_eq!(Some(HTMLToken::Eof), tokenizer.next());
+        assert_eq!(None, tokenizer.next());
+        assert_eq!(1, tokenizer.errors().len());
+    }
+
+    #[test]
+    fn test_attributes_separated_by_whitespace() {
+        let html = "<input\ttype=\"text\"\nname=\"q\">".to_string();
+        let mut tokenizer = HtmlTokenizer::new(html);
+        let mut attr1 = Attribute::new();
+        attr1.add_char('t', true);
+        attr1.add_char('y', true);
+        attr1.add_char('p', true);
+        attr1.add_char('e', true);
+        attr1.add_char('t', false);
+        attr1.add_char('e', false);
+        attr1.add_char('x', false);
+        attr1.add_char('t', false);
+
+        let mut attr2 = Attribute::new();
+        attr2.add_char('n', true);
+        attr2.add_char('a', true);
+        attr2.add_char('m', true);
+        attr2.add_char('e', true);
+        attr2.add_char('q', false);
+
+        let expected = HTMLToken::StartTag {
+            tag: "input".to_string(),
+            original_tag: "input".to_string(),
+            self_closing: false,
+            attributes: vec![attr1, attr2],
+        };
+        assert_eq!(Some(expected), tokenizer.next());
+    }
+
+    #[test]
+    fn test_title_raw_text() {
+        let html = "<title>1 < 2</title>".to_string();
+        let mut tokenizer = HtmlTokenizer::new(html);
+        let expected = [
+            HTMLToken::StartTag {
+                tag: "title".to_string(),
+                original_tag: "title".to_string(),
+                self_closing: false,
+                attributes: Vec::new(),
+            },
+            HTMLToken::Char('1'),
+            HTMLToken::Char(' '),
+            HTMLToken::Char('<'),
+            HTMLToken::Char(' '),
+            HTMLToken::Char('2'),
+            HTMLToken::EndTag {
+                tag: "title".to_string(),
+                original_tag: "title".to_string(),
+            },
+            HTMLToken::Eof,
+        ];
+        for e in expected {
+            assert_eq!(Some(e), tokenizer.next());
+        }
+    }
+
+    #[test]
+    fn test_raw_text_ignores_other_end_tags() {
+        let html = "<textarea><b></b></textarea><p>".to_string();
+        let mut tokenizer = HtmlTokenizer::new(html);
+        assert_eq!(
+            Some(HTMLToken::StartTag {
+                tag: "textarea".to_string(),
+                original_tag: "textarea".to_string(),
+                self_closing: false,
+                attributes: Vec::new(),
+            }),
+            tokenizer.next()
+        );
+        for c in "<b></b>".chars() {
+            assert_eq!(Some(HTMLToken::Char(c)), tokenizer.next());
+        }
+        assert_eq!(
+            Some(HTMLToken::EndTag {
+                tag: "textarea".to_string(),
+                original_tag: "textarea".to_string(),
+            }),
+            tokenizer.next()
+        );
+        assert_eq!(
+            Some(HTMLToken::StartTag {
+                tag: "p".to_string(),
+                original_tag: "p".to_string(),
+                self_closing: false,
+                attributes: Vec::new(),
+            }),
+            tokenizer.next()
+        );
+    }
+
+    #[test]
+    fn test_duplicate_attributes() {
+        let html = "<p id=\"a\" id=\"b\">".to_string();
+        let mut tokenizer = HtmlTokenizer::new(html);
+        let mut attr = Attribute::new();
+        attr.add_char('i', true);
+        attr.add_char('d', true);
+        attr.add_char('a', false);
+
+        let expected = HTMLToken::StartTag {
+            tag: "p".to_string(),
+            original_tag: "p".to_string(),
+            self_closing: false,
+            attributes: vec![attr],
+        };
+        assert_eq!(Some(expected), tokenizer.next());
+    }
+
+    #[test]
+    fn test_null_character() {
+        let html = "<p>a\0b</p>".to_string();
+        let mut tokenizer = HtmlTokenizer::new(html);
+        let expected = [
+            HTMLToken::StartTag {
+                tag: "p".to_string(),
+                original_tag: "p".to_string(),
+                self_closing: false,
+                attributes: Vec::new(),
+            },
+            HTMLToken::Char('a'),
+            HTMLToken::Char('\u{FFFD}'),
+            HTMLToken::Char('b'),
+            HTMLToken::EndTag {
+                tag: "p".to_string(),
+                original_tag: "p".to_string(),
+            },
+        ];
+        for e in expected {
+            assert_eq!(Some(e), tokenizer.next());
+        }
+    }
+
+    #[test]
+    fn test_null_character_in_attribute_value() {
+        let html = "<p id=\"a\0\">".to_string();
+        let mut tokenizer = HtmlTokenizer::new(html);
+        let mut attr = Attribute::new();
+        attr.add_char('i', true);
+        attr.add_char('d', true);
+        attr.add_char('a', false);
+        attr.add_char('\u{FFFD}', false);
+
+        let expected = HTMLToken::StartTag {
+            tag: "p".to_string(),
+            original_tag: "p".to_string(),
+            self_closing: false,
+            attributes: vec![attr],
+        };
+        assert_eq!(Some(expected), tokenizer.next());
+    }
+
+    #[test]
+    fn test_peek() {
+        let html = "<p>a".to_string();
+        let mut tokenizer = HtmlTokenizer::new(html);
+        let p = HTMLToken::StartTag {
+            tag: "p".to_string(),
+            original_tag: "p".to_string(),
+            self_closing: false,
+            attributes: Vec::new(),
+        };
+
+        assert_eq!(Some(&p), tokenizer.peek());
+        assert_eq!(Some(&p), tokenizer.peek());
+        assert_eq!(Some(p), tokenizer.next());
+        assert_eq!(Some(HTMLToken::Char('a')), tokenizer.next());
+        assert_eq!(Some(&HTMLToken::Eof), tokenizer.peek());
+        assert_eq!(Some(HTMLToken::Eof), tokenizer.next());
+        assert_eq!(None, tokenizer.peek());
+        assert_eq!(None, tokenizer.next());
+    }
+
+    #[test]
+    fn test_original_tag() {
+        let html = "<DiV></DIV>".to_string();
+        let mut tokenizer = HtmlTokenizer::new(html);
+        let expected = [
+            HTMLToken::StartTag {
+                tag: "div".to_string(),
+                original_tag: "DiV".to_string(),
+                self_closing: false,
+                attributes: Vec::new(),
+            },
+            HTMLToken::EndTag {
+                tag: "div".to_string(),
+                original_tag: "DIV".to_string(),
             },
         ];
         for e in expected {
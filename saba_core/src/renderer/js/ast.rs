@@ -1,5 +1,5 @@
 use crate::renderer::js::token::{JsLexer, Token};
-use alloc::{rc::Rc, vec::Vec};
+use alloc::{rc::Rc, string::String, vec::Vec};
 use core::iter::Peekable;
 
 // AST を構築する構造体
@@ -40,7 +40,18 @@ impl JsParser {
     }
 
     fn statement(&mut self) -> Option<Rc<Node>> {
-        let node = Node::new_expression_statement(self.assignment_expression());
+        let t = match self.t.peek() {
+            Some(t) => t.clone(),
+            None => return None,
+        };
+
+        let node = match t {
+            Token::Keyword(keyword) if keyword == "var" => self.variable_declaration(),
+            Token::Keyword(keyword) if keyword == "for" => return self.for_statement(),
+            Token::Keyword(keyword) if keyword == "if" => return self.if_statement(),
+            Token::Punctuator('{') => return self.block_statement(),
+            _ => Node::new_expression_statement(self.assignment_expression()),
+        };
 
         if let Some(Token::Punctuator(c)) = self.t.peek() {
             // ';' を消費する
@@ -52,37 +63,238 @@ impl JsParser {
         node
     }
 
-    fn assignment_expression(&mut self) -> Option<Rc<Node>> {
-        self.additive_expression()
+    // { から } までの文の並び
+    fn block_statement(&mut self) -> Option<Rc<Node>> {
+        // '{' を消費する
+        assert!(self.t.next().is_some());
+
+        let mut body = Vec::new();
+        loop {
+            match self.t.peek() {
+                Some(Token::Punctuator('}')) => {
+                    assert!(self.t.next().is_some());
+                    break;
+                }
+                Some(_) => body.push(self.statement()),
+                None => break,
+            }
+        }
+
+        Node::new_block_statement(body)
     }
 
-    fn additive_expression(&mut self) -> Option<Rc<Node>> {
-        let left = self.left_hand_side_expression();
+    // for (init; test; update) body
+    fn for_statement(&mut self) -> Option<Rc<Node>> {
+        // "for" を消費する
+        assert!(self.t.next().is_some());
+        self.expect_punctuator('(');
 
-        let t = match self.t.peek() {
-            Some(t) => t.clone(),
-            None => return left,
+        let init = match self.t.peek() {
+            Some(Token::Punctuator(';')) => None,
+            Some(Token::Keyword(keyword)) if keyword == "var" => self.variable_declaration(),
+            _ => self.assignment_expression(),
         };
+        self.expect_punctuator(';');
 
-        match t {
-            Token::Punctuator(c) => match c {
-                '+' | '-' => {
+        let test = match self.t.peek() {
+            Some(Token::Punctuator(';')) => None,
+            _ => self.assignment_expression(),
+        };
+        self.expect_punctuator(';');
+
+        let update = match self.t.peek() {
+            Some(Token::Punctuator(')')) => None,
+            _ => self.assignment_expression(),
+        };
+        self.expect_punctuator(')');
+
+        let body = self.statement();
+
+        Node::new_for_statement(init, test, update, body)
+    }
+
+    // else 節は省略できる。else は直前の if と対応する
+    fn if_statement(&mut self) -> Option<Rc<Node>> {
+        // "if" を消費する
+        assert!(self.t.next().is_some());
+        self.expect_punctuator('(');
+        let test = self.assignment_expression();
+        self.expect_punctuator(')');
+
+        let consequent = self.statement();
+
+        let alternate = match self.t.peek() {
+            Some(Token::Keyword(keyword)) if keyword == "else" => {
+                // "else" を消費する
+                assert!(self.t.next().is_some());
+                self.statement()
+            }
+            _ => None,
+        };
+
+        Node::new_if_statement(test, consequent, alternate)
+    }
+
+    // 次のトークンが c であれば消費する
+    fn expect_punctuator(&mut self, c: char) {
+        if self.t.peek() == Some(&Token::Punctuator(c)) {
+            assert!(self.t.next().is_some());
+        }
+    }
+
+    // var x = 1
+    fn variable_declaration(&mut self) -> Option<Rc<Node>> {
+        // "var" を消費する
+        assert!(self.t.next().is_some());
+
+        let id = match self.t.next() {
+            Some(Token::Identifier(name)) => Node::new_identifier(name),
+            _ => return None,
+        };
+
+        let init = match self.t.peek() {
+            Some(Token::Punctuator('=')) => {
+                assert!(self.t.next().is_some());
+                self.assignment_expression()
+            }
+            _ => None,
+        };
+
+        Node::new_variable_declaration(id, init)
+    }
+
+    fn assignment_expression(&mut self) -> Option<Rc<Node>> {
+        let expr = self.equality_expression();
+
+        match self.t.peek() {
+            Some(Token::Punctuator('=')) => {
+                // '=' を消費する
+                assert!(self.t.next().is_some());
+                Node::new_assignment_expression('=', expr, self.assignment_expression())
+            }
+            _ => expr,
+        }
+    }
+
+    // '==' と '!=' は '<' と '>' より弱く結合する
+    fn equality_expression(&mut self) -> Option<Rc<Node>> {
+        let mut left = self.relational_expression();
+
+        loop {
+            match self.t.peek() {
+                Some(Token::Operator(op)) if op == "==" || op == "!=" => {
+                    let op = op.clone();
                     // 演算子を消費する
                     assert!(self.t.next().is_some());
-                    Node::new_additive_expression(c, left, self.assignment_expression())
+                    left = Node::new_equality_expression(op, left, self.relational_expression());
                 }
-                _ => left,
-            },
+                _ => return left,
+            }
+        }
+    }
+
+    fn relational_expression(&mut self) -> Option<Rc<Node>> {
+        let left = self.additive_expression();
+
+        match self.t.peek() {
+            Some(Token::Punctuator(c)) if *c == '<' || *c == '>' => {
+                let c = *c;
+                // 演算子を消費する
+                assert!(self.t.next().is_some());
+                Node::new_relational_expression(c, left, self.additive_expression())
+            }
             _ => left,
         }
     }
 
+    fn additive_expression(&mut self) -> Option<Rc<Node>> {
+        let mut left = self.multiplicative_expression();
+
+        // 左結合になるように、演算子が続く限り左辺を組み立てていく
+        loop {
+            match self.t.peek() {
+                Some(Token::Punctuator(c)) if *c == '+' || *c == '-' => {
+                    let c = *c;
+                    // 演算子を消費する
+                    assert!(self.t.next().is_some());
+                    left = Node::new_additive_expression(c, left, self.multiplicative_expression());
+                }
+                _ => return left,
+            }
+        }
+    }
+
+    // '*' と '/' は '+' と '-' より優先して結合する
+    fn multiplicative_expression(&mut self) -> Option<Rc<Node>> {
+        let mut left = self.left_hand_side_expression();
+
+        loop {
+            match self.t.peek() {
+                Some(Token::Punctuator(c)) if *c == '*' || *c == '/' => {
+                    let c = *c;
+                    // 演算子を消費する
+                    assert!(self.t.next().is_some());
+                    left = Node::new_multiplicative_expression(
+                        c,
+                        left,
+                        self.left_hand_side_expression(),
+                    );
+                }
+                _ => return left,
+            }
+        }
+    }
+
     fn left_hand_side_expression(&mut self) -> Option<Rc<Node>> {
-        self.member_expression()
+        let expr = self.member_expression();
+
+        match self.t.peek() {
+            Some(Token::Punctuator('(')) => {
+                // '(' を消費する
+                assert!(self.t.next().is_some());
+                Node::new_call_expression(expr, self.arguments())
+            }
+            _ => expr,
+        }
+    }
+
+    // 関数呼び出しの引数。閉じ括弧 ')' まで消費する
+    fn arguments(&mut self) -> Vec<Option<Rc<Node>>> {
+        let mut arguments = Vec::new();
+
+        loop {
+            match self.t.peek() {
+                Some(Token::Punctuator(')')) => {
+                    assert!(self.t.next().is_some());
+                    return arguments;
+                }
+                Some(Token::Punctuator(',')) => {
+                    assert!(self.t.next().is_some());
+                }
+                Some(_) => arguments.push(self.assignment_expression()),
+                None => return arguments,
+            }
+        }
     }
 
     fn member_expression(&mut self) -> Option<Rc<Node>> {
-        self.primary_expression()
+        let mut expr = self.primary_expression();
+
+        // "a.b.c" のようにプロパティへのアクセスが続く限り組み立てていく
+        while let Some(Token::Punctuator('.')) = self.t.peek() {
+            // '.' を消費する
+            assert!(self.t.next().is_some());
+            expr = Node::new_member_expression(expr, self.identifier());
+        }
+
+        expr
+    }
+
+    fn identifier(&mut self) -> Option<Rc<Node>> {
+        match self.t.next() {
+            Some(Token::Identifier(name)) => Node::new_identifier(name),
+            _ => None,
+        }
     }
 
     fn primary_expression(&mut self) -> Option<Rc<Node>> {
@@ -93,12 +305,15 @@ impl JsParser {
 
         match t {
             Token::Number(value) => Node::new_numeric_literal(value),
+            Token::Identifier(name) => Node::new_identifier(name),
+            Token::StringLiteral(value) => Node::new_string_literal(value),
+            Token::Keyword(keyword) if keyword == "null" => Some(Rc::new(Node::NullLiteral)),
             _ => None,
         }
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Program {
     body: Vec<Rc<Node>>,
 }
@@ -117,7 +332,7 @@ impl Program {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Node {
     ExpressionStatement(Option<Rc<Node>>),
     AdditiveExpression {
@@ -125,6 +340,11 @@ pub enum Node {
         left: Option<Rc<Node>>,
         right: Option<Rc<Node>>,
     },
+    MultiplicativeExpression {
+        operator: char,
+        left: Option<Rc<Node>>,
+        right: Option<Rc<Node>>,
+    },
     AssignmentExpression {
         operator: char,
         left: Option<Rc<Node>>,
@@ -134,7 +354,45 @@ pub enum Node {
         object: Option<Rc<Node>>,
         property: Option<Rc<Node>>,
     },
-    NumericLiteral(u64),
+    CallExpression {
+        callee: Option<Rc<Node>>,
+        arguments: Vec<Option<Rc<Node>>>,
+    },
+    RelationalExpression {
+        operator: char,
+        left: Option<Rc<Node>>,
+        right: Option<Rc<Node>>,
+    },
+    EqualityExpression {
+        operator: String,
+        left: Option<Rc<Node>>,
+        right: Option<Rc<Node>>,
+    },
+    VariableDeclaration {
+        declarations: Vec<Option<Rc<Node>>>,
+    },
+    VariableDeclarator {
+        id: Option<Rc<Node>>,
+        init: Option<Rc<Node>>,
+    },
+    BlockStatement {
+        body: Vec<Option<Rc<Node>>>,
+    },
+    IfStatement {
+        test: Option<Rc<Node>>,
+        consequent: Option<Rc<Node>>,
+        alternate: Option<Rc<Node>>,
+    },
+    ForStatement {
+        init: Option<Rc<Node>>,
+        test: Option<Rc<Node>>,
+        update: Option<Rc<Node>>,
+        body: Option<Rc<Node>>,
+    },
+    Identifier(String),
+    NumericLiteral(f64),
+    StringLiteral(String),
+    NullLiteral,
 }
 
 impl Node {
@@ -154,6 +412,18 @@ impl Node {
         }))
     }
 
+    pub fn new_multiplicative_expression(
+        operator: char,
+        left: Option<Rc<Self>>,
+        right: Option<Rc<Self>>,
+    ) -> Option<Rc<Self>> {
+        Some(Rc::new(Self::MultiplicativeExpression {
+            operator,
+            left,
+            right,
+        }))
+    }
+
     pub fn new_assignment_expression(
         operator: char,
         left: Option<Rc<Self>>,
@@ -173,9 +443,87 @@ impl Node {
         Some(Rc::new(Self::MemberExpression { object, property }))
     }
 
-    pub fn new_numeric_literal(value: u64) -> Option<Rc<Self>> {
+    pub fn new_call_expression(
+        callee: Option<Rc<Self>>,
+        arguments: Vec<Option<Rc<Self>>>,
+    ) -> Option<Rc<Self>> {
+        Some(Rc::new(Self::CallExpression { callee, arguments }))
+    }
+
+    pub fn new_relational_expression(
+        operator: char,
+        left: Option<Rc<Self>>,
+        right: Option<Rc<Self>>,
+    ) -> Option<Rc<Self>> {
+        Some(Rc::new(Self::RelationalExpression {
+            operator,
+            left,
+            right,
+        }))
+    }
+
+    pub fn new_equality_expression(
+        operator: String,
+        left: Option<Rc<Self>>,
+        right: Option<Rc<Self>>,
+    ) -> Option<Rc<Self>> {
+        Some(Rc::new(Self::EqualityExpression {
+            operator,
+            left,
+            right,
+        }))
+    }
+
+    pub fn new_variable_declaration(
+        id: Option<Rc<Self>>,
+        init: Option<Rc<Self>>,
+    ) -> Option<Rc<Self>> {
+        Some(Rc::new(Self::VariableDeclaration {
+            declarations: [Some(Rc::new(Self::VariableDeclarator { id, init }))].to_vec(),
+        }))
+    }
+
+    pub fn new_block_statement(body: Vec<Option<Rc<Self>>>) -> Option<Rc<Self>> {
+        Some(Rc::new(Self::BlockStatement { body }))
+    }
+
+    pub fn new_if_statement(
+        test: Option<Rc<Self>>,
+        consequent: Option<Rc<Self>>,
+        alternate: Option<Rc<Self>>,
+    ) -> Option<Rc<Self>> {
+        Some(Rc::new(Self::IfStatement {
+            test,
+            consequent,
+            alternate,
+        }))
+    }
+
+    pub fn new_for_statement(
+        init: Option<Rc<Self>>,
+        test: Option<Rc<Self>>,
+        update: Option<Rc<Self>>,
+        body: Option<Rc<Self>>,
+    ) -> Option<Rc<Self>> {
+        Some(Rc::new(Self::ForStatement {
+            init,
+            test,
+            update,
+            body,
+        }))
+    }
+
+    pub fn new_identifier(name: String) -> Option<Rc<Self>> {
+        Some(Rc::new(Self::Identifier(name)))
+    }
+
+    pub fn new_numeric_literal(value: f64) -> Option<Rc<Self>> {
         Some(Rc::new(Self::NumericLiteral(value)))
     }
+
+    pub fn new_string_literal(value: String) -> Option<Rc<Self>> {
+        Some(Rc::new(Self::StringLiteral(value)))
+    }
 }
 
 #[cfg(test)]
@@ -205,7 +553,7 @@ mod tests {
         let mut body = Vec::new();
 
         body.push(Rc::new(Node::ExpressionStatement(Some(Rc::new(
-            Node::NumericLiteral(42),
+            Node::NumericLiteral(42.0),
         )))));
 
         expected.set_body(body);
@@ -224,12 +572,184 @@ mod tests {
         body.push(Rc::new(Node::ExpressionStatement(Some(Rc::new(
             Node::AdditiveExpression {
                 operator: '+',
-                left: Some(Rc::new(Node::NumericLiteral(1))),
-                right: Some(Rc::new(Node::NumericLiteral(2))),
+                left: Some(Rc::new(Node::NumericLiteral(1.0))),
+                right: Some(Rc::new(Node::NumericLiteral(2.0))),
             },
         )))));
 
         expected.set_body(body);
         assert_eq!(expected, parser.parse_ast());
     }
+
+    #[test]
+    fn test_multiplicative_precedence() {
+        let input = "1 + 2 * 3".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+
+        let mut expected = Program::new();
+        let body = [Rc::new(Node::ExpressionStatement(Some(Rc::new(
+            Node::AdditiveExpression {
+                operator: '+',
+                left: Some(Rc::new(Node::NumericLiteral(1.0))),
+                right: Some(Rc::new(Node::MultiplicativeExpression {
+                    operator: '*',
+                    left: Some(Rc::new(Node::NumericLiteral(2.0))),
+                    right: Some(Rc::new(Node::NumericLiteral(3.0))),
+                })),
+            },
+        ))))]
+        .to_vec();
+
+        expected.set_body(body);
+        assert_eq!(expected, parser.parse_ast());
+    }
+
+    #[test]
+    fn test_variable_declaration() {
+        let input = "var x = 1;".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let mut expected = Program::new();
+        let body = [Rc::new(Node::VariableDeclaration {
+            declarations: [Some(Rc::new(Node::VariableDeclarator {
+                id: Some(Rc::new(Node::Identifier("x".to_string()))),
+                init: Some(Rc::new(Node::NumericLiteral(1.0))),
+            }))]
+            .to_vec(),
+        })]
+        .to_vec();
+        expected.set_body(body);
+        assert_eq!(expected, parser.parse_ast());
+    }
+
+    #[test]
+    fn test_assignment_expression() {
+        let input = "x = 1 + 2".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let mut expected = Program::new();
+        let body = [Rc::new(Node::ExpressionStatement(Some(Rc::new(
+            Node::AssignmentExpression {
+                operator: '=',
+                left: Some(Rc::new(Node::Identifier("x".to_string()))),
+                right: Some(Rc::new(Node::AdditiveExpression {
+                    operator: '+',
+                    left: Some(Rc::new(Node::NumericLiteral(1.0))),
+                    right: Some(Rc::new(Node::NumericLiteral(2.0))),
+                })),
+            },
+        ))))]
+        .to_vec();
+        expected.set_body(body);
+        assert_eq!(expected, parser.parse_ast());
+    }
+
+    #[test]
+    fn test_equality_expression() {
+        let input = "1 < 2 == 3".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let mut expected = Program::new();
+        let body = [Rc::new(Node::ExpressionStatement(Some(Rc::new(
+            Node::EqualityExpression {
+                operator: "==".to_string(),
+                left: Some(Rc::new(Node::RelationalExpression {
+                    operator: '<',
+                    left: Some(Rc::new(Node::NumericLiteral(1.0))),
+                    right: Some(Rc::new(Node::NumericLiteral(2.0))),
+                })),
+                right: Some(Rc::new(Node::NumericLiteral(3.0))),
+            },
+        ))))]
+        .to_vec();
+        expected.set_body(body);
+        assert_eq!(expected, parser.parse_ast());
+    }
+
+    #[test]
+    fn test_if_statement() {
+        let input = "if (x) y = 1; else { y = 2; }".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let assign = |value: f64| {
+            Some(Rc::new(Node::ExpressionStatement(Some(Rc::new(
+                Node::AssignmentExpression {
+                    operator: '=',
+                    left: Some(Rc::new(Node::Identifier("y".to_string()))),
+                    right: Some(Rc::new(Node::NumericLiteral(value))),
+                },
+            )))))
+        };
+        let mut expected = Program::new();
+        let body = [Rc::new(Node::IfStatement {
+            test: Some(Rc::new(Node::Identifier("x".to_string()))),
+            consequent: assign(1.0),
+            alternate: Some(Rc::new(Node::BlockStatement {
+                body: [assign(2.0)].to_vec(),
+            })),
+        })]
+        .to_vec();
+        expected.set_body(body);
+        assert_eq!(expected, parser.parse_ast());
+    }
+
+    #[test]
+    fn test_for_statement() {
+        let input = "for (var i = 0; i < 4; i = i + 1) {}".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+
+        let mut expected = Program::new();
+        let mut body = Vec::new();
+
+        let i = || Some(Rc::new(Node::Identifier("i".to_string())));
+        body.push(Rc::new(Node::ForStatement {
+            init: Node::new_variable_declaration(i(), Node::new_numeric_literal(0.0)),
+            test: Node::new_relational_expression('<', i(), Node::new_numeric_literal(4.0)),
+            update: Node::new_assignment_expression(
+                '=',
+                i(),
+                Node::new_additive_expression('+', i(), Node::new_numeric_literal(1.0)),
+            ),
+            body: Node::new_block_statement(Vec::new()),
+        }));
+
+        expected.set_body(body);
+        assert_eq!(expected, parser.parse_ast());
+    }
+
+    #[test]
+    fn test_string_literal() {
+        let input = "\"hi\"".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let mut expected = Program::new();
+        let body = [Rc::new(Node::ExpressionStatement(Some(Rc::new(
+            Node::StringLiteral("hi".to_string()),
+        ))))]
+        .to_vec();
+        expected.set_body(body);
+        assert_eq!(expected, parser.parse_ast());
+    }
+
+    #[test]
+    fn test_call_member_expression() {
+        let input = "\"hello\".charAt(1)".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let mut expected = Program::new();
+        let body = [Rc::new(Node::ExpressionStatement(Some(Rc::new(
+            Node::CallExpression {
+                callee: Some(Rc::new(Node::MemberExpression {
+                    object: Some(Rc::new(Node::StringLiteral("hello".to_string()))),
+                    property: Some(Rc::new(Node::Identifier("charAt".to_string()))),
+                })),
+                arguments: [Some(Rc::new(Node::NumericLiteral(1.0)))].to_vec(),
+            },
+        ))))]
+        .to_vec();
+        expected.set_body(body);
+        assert_eq!(expected, parser.parse_ast());
+    }
 }
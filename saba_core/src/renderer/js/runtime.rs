@@ -1,14 +1,72 @@
-use crate::renderer::js::ast::{Node, Program};
+use crate::error::Error;
+use crate::renderer::js::ast::{JsParser, Node, Program};
+use crate::renderer::js::token::JsLexer;
+use alloc::format;
 use alloc::rc::Rc;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
 use core::borrow::Borrow;
+use core::cell::RefCell;
+use core::fmt::{Display, Formatter};
 use core::ops::{Add, Sub};
 
+// ループ1つあたりの繰り返し回数の上限。これを超えたら無限ループとみなして打ち切る
+const MAX_LOOP_ITERATIONS: usize = 10000;
+
+// 変数名と値の組を保持する環境
+#[derive(Debug, Clone, PartialEq)]
+pub struct Environment {
+    variables: Vec<(String, Option<RuntimeValue>)>,
+}
+
+impl Environment {
+    fn new() -> Self {
+        Self {
+            variables: Vec::new(),
+        }
+    }
+
+    pub fn get_variable(&self, name: &str) -> Option<RuntimeValue> {
+        self.variables
+            .iter()
+            .find(|(n, _)| n == name)
+            .and_then(|(_, value)| value.clone())
+    }
+
+    fn add_variable(&mut self, name: String, value: Option<RuntimeValue>) {
+        self.update_variable(name, value);
+    }
+
+    // 宣言されていない変数への代入は、新しい変数として追加する
+    fn update_variable(&mut self, name: String, value: Option<RuntimeValue>) {
+        match self.variables.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, v)) => *v = value,
+            None => self.variables.push((name, value)),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
-pub struct JsRuntime {}
+pub struct JsRuntime {
+    env: Rc<RefCell<Environment>>,
+    logs: Vec<String>, // console.log で出力された文字列
+}
 
 impl JsRuntime {
     pub fn new() -> Self {
-        Self {}
+        Self {
+            env: Rc::new(RefCell::new(Environment::new())),
+            logs: Vec::new(),
+        }
+    }
+
+    pub fn env(&self) -> Rc<RefCell<Environment>> {
+        self.env.clone()
+    }
+
+    // console.log で出力された文字列を、出力された順に返す
+    pub fn logs(&self) -> &[String] {
+        &self.logs
     }
 
     pub fn execute(&mut self, program: &Program) {
@@ -17,6 +75,40 @@ impl JsRuntime {
         }
     }
 
+    // source を字句解析・構文解析して実行し、最後の式文の値を返す
+    // 式文がなければ None
+    pub fn run_source(&mut self, source: String) -> Option<RuntimeValue> {
+        let program = JsParser::new(JsLexer::new(source)).parse_ast();
+
+        let mut result = None;
+        for node in program.body() {
+            let value = self.evaluate(&Some(node.clone()));
+            if let Node::ExpressionStatement(_) = node.borrow() {
+                result = value;
+            }
+        }
+        result
+    }
+
+    // プログラムを実行し、最後の文の値を返す。値を持たない文の値は undefined
+    // 式文の評価に失敗した場合（宣言されていない変数の参照など）はエラーを返す
+    pub fn evaluate_program(&mut self, program: &Program) -> Result<RuntimeValue, Error> {
+        let mut result = RuntimeValue::Undefined;
+        for node in program.body() {
+            result = match (self.evaluate(&Some(node.clone())), node.borrow()) {
+                (Some(value), _) => value,
+                (None, Node::ExpressionStatement(_)) => {
+                    return Err(Error::UnexpectedInput(format!(
+                        "failed to evaluate {:?}",
+                        node
+                    )))
+                }
+                (None, _) => RuntimeValue::Undefined,
+            };
+        }
+        Ok(result)
+    }
+
     fn evaluate(&mut self, node: &Option<Rc<Node>>) -> Option<RuntimeValue> {
         let node = match node {
             Some(node) => node,
@@ -48,37 +140,383 @@ impl JsRuntime {
                     None
                 }
             }
+            Node::MultiplicativeExpression {
+                operator,
+                left,
+                right,
+            } => {
+                let left_value = self.evaluate(left)?.to_number();
+                let right_value = self.evaluate(right)?.to_number();
+
+                match operator {
+                    '*' => Some(RuntimeValue::Number(left_value * right_value)),
+                    '/' => Some(RuntimeValue::Number(left_value / right_value)),
+                    _ => None,
+                }
+            }
             Node::AssignmentExpression {
-                operator: _,
-                left: _,
-                right: _,
+                operator,
+                left,
+                right,
+            } => {
+                if operator != &'=' {
+                    return None;
+                }
+
+                // 代入先は識別子のみ対応
+                let name = match left.as_deref() {
+                    Some(Node::Identifier(name)) => name.clone(),
+                    _ => return None,
+                };
+
+                let value = self.evaluate(right);
+                self.env.borrow_mut().update_variable(name, value.clone());
+                value
+            }
+            Node::RelationalExpression {
+                operator,
+                left,
+                right,
+            } => {
+                let left_value = self.evaluate(left)?.to_number();
+                let right_value = self.evaluate(right)?.to_number();
+
+                match operator {
+                    '<' => Some(RuntimeValue::Boolean(left_value < right_value)),
+                    '>' => Some(RuntimeValue::Boolean(left_value > right_value)),
+                    _ => None,
+                }
+            }
+            Node::EqualityExpression {
+                operator,
+                left,
+                right,
+            } => {
+                let left_value = self.evaluate(left)?;
+                let right_value = self.evaluate(right)?;
+                let equal = left_value.loosely_equals(&right_value);
+
+                match operator.as_str() {
+                    "==" => Some(RuntimeValue::Boolean(equal)),
+                    "!=" => Some(RuntimeValue::Boolean(!equal)),
+                    _ => None,
+                }
+            }
+            Node::VariableDeclaration { declarations } => {
+                for declaration in declarations {
+                    self.evaluate(declaration);
+                }
+                None
+            }
+            Node::VariableDeclarator { id, init } => {
+                let name = match id.as_deref() {
+                    Some(Node::Identifier(name)) => name.clone(),
+                    _ => return None,
+                };
+
+                // 初期化されていない変数の値は undefined
+                let value = match init {
+                    Some(_) => self.evaluate(init),
+                    None => Some(RuntimeValue::Undefined),
+                };
+                self.env.borrow_mut().add_variable(name, value);
+                None
+            }
+            Node::BlockStatement { body } => {
+                for statement in body {
+                    self.evaluate(statement);
+                }
+                None
+            }
+            // test が真とみなされる値なら consequent を、そうでなければ alternate を実行する
+            Node::IfStatement {
+                test,
+                consequent,
+                alternate,
             } => {
-                // 後ほど実装
+                let is_truthy = self.evaluate(test).map_or(false, |v| v.is_truthy());
+                if is_truthy {
+                    self.evaluate(consequent);
+                } else {
+                    self.evaluate(alternate);
+                }
                 None
             }
-            Node::MemberExpression {
-                object: _,
-                property: _,
+            // init は最初に1回だけ、test は毎回の繰り返しの前、update は本体の後に評価する
+            // 変数はループの外側と同じ環境に置かれる
+            Node::ForStatement {
+                init,
+                test,
+                update,
+                body,
             } => {
-                // 後ほど実装
+                self.evaluate(init);
+
+                for _ in 0..MAX_LOOP_ITERATIONS {
+                    if test.is_some() {
+                        match self.evaluate(test) {
+                            Some(value) if value.is_truthy() => {}
+                            _ => break,
+                        }
+                    }
+
+                    self.evaluate(body);
+                    self.evaluate(update);
+                }
                 None
             }
+            Node::Identifier(name) => match RefCell::borrow(&self.env).get_variable(name) {
+                Some(value) => Some(value),
+                // 同じ名前の変数が宣言されていなければ、組み込みの undefined として扱う
+                None if name == "undefined" => Some(RuntimeValue::Undefined),
+                None => None,
+            },
+            Node::MemberExpression { object, property } => {
+                let object_value = self.evaluate(object)?;
+                let name = match property.as_deref() {
+                    Some(Node::Identifier(name)) => name.clone(),
+                    _ => return None,
+                };
+
+                match (object_value, name.as_str()) {
+                    (RuntimeValue::String(s), "length") => {
+                        Some(RuntimeValue::Number(s.chars().count() as f64))
+                    }
+                    // undefined と null のプロパティは読めない
+                    (RuntimeValue::Undefined, _) | (RuntimeValue::Null, _) => None,
+                    // 存在しないプロパティの値は undefined
+                    _ => Some(RuntimeValue::Undefined),
+                }
+            }
+            Node::CallExpression { callee, arguments } => {
+                // メソッド呼び出しのみ対応
+                let (object, property) = match callee.as_deref() {
+                    Some(Node::MemberExpression { object, property }) => (object, property),
+                    _ => return None,
+                };
+                let method = match property.as_deref() {
+                    Some(Node::Identifier(name)) => name.clone(),
+                    _ => return None,
+                };
+
+                let mut args = Vec::new();
+                for argument in arguments {
+                    args.push(self.evaluate(argument));
+                }
+
+                // Math と console は同じ名前の変数が宣言されていなければ組み込みのオブジェクトとして扱う
+                if let Some(Node::Identifier(name)) = object.as_deref() {
+                    if RefCell::borrow(&self.env).get_variable(name).is_none() {
+                        match (name.as_str(), method.as_str()) {
+                            ("Math", _) => return Self::call_math_function(&method, &args),
+                            ("console", "log") => {
+                                self.console_log(&args);
+                                return Some(RuntimeValue::Undefined);
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+
+                let object_value = self.evaluate(object)?;
+                match object_value {
+                    RuntimeValue::String(s) => Self::call_string_method(&s, &method, &args),
+                    _ => None,
+                }
+            }
             Node::NumericLiteral(value) => Some(RuntimeValue::Number(*value)),
+            Node::StringLiteral(value) => Some(RuntimeValue::String(value.clone())),
+            Node::NullLiteral => Some(RuntimeValue::Null),
         }
     }
+
+    // 引数を文字列に変換し、空白で区切って1行として記録する
+    fn console_log(&mut self, args: &[Option<RuntimeValue>]) {
+        let line = args
+            .iter()
+            .map(|arg| match arg {
+                Some(value) => value.to_string(),
+                None => "undefined".to_string(),
+            })
+            .collect::<Vec<String>>()
+            .join(" ");
+        self.logs.push(line);
+    }
+
+    // 文字列のメソッドを呼び出す。位置は文字単位で数える
+    fn call_string_method(
+        s: &str,
+        method: &str,
+        args: &[Option<RuntimeValue>],
+    ) -> Option<RuntimeValue> {
+        let arg = args.first().cloned().flatten();
+
+        match method {
+            // 範囲外の位置を指定した場合は空文字列を返す
+            "charAt" => {
+                let index = arg.map(|a| a.to_number()).unwrap_or(0.0);
+                let c = if index < 0.0 || index.is_nan() {
+                    None
+                } else {
+                    s.chars().nth(index as usize)
+                };
+                Some(RuntimeValue::String(
+                    c.map(|c| c.to_string()).unwrap_or_default(),
+                ))
+            }
+            // 見つからない場合は -1 を返す
+            "indexOf" => {
+                let sub = arg?.to_string();
+                let index = match s.find(&sub) {
+                    Some(byte_index) => s[..byte_index].chars().count() as f64,
+                    None => -1.0,
+                };
+                Some(RuntimeValue::Number(index))
+            }
+            _ => None,
+        }
+    }
+
+    // Math オブジェクトの関数を呼び出す
+    fn call_math_function(name: &str, args: &[Option<RuntimeValue>]) -> Option<RuntimeValue> {
+        let numbers: Vec<f64> = args
+            .iter()
+            .map(|a| a.as_ref().map_or(f64::NAN, |v| v.to_number()))
+            .collect();
+        let first = numbers.first().copied().unwrap_or(f64::NAN);
+
+        let result = match name {
+            // 引数がない場合、max は負の無限大、min は正の無限大を返す
+            "max" => numbers.iter().fold(f64::NEG_INFINITY, |acc, n| {
+                if acc.is_nan() || n.is_nan() {
+                    f64::NAN
+                } else if *n > acc {
+                    *n
+                } else {
+                    acc
+                }
+            }),
+            "min" => numbers.iter().fold(f64::INFINITY, |acc, n| {
+                if acc.is_nan() || n.is_nan() {
+                    f64::NAN
+                } else if *n < acc {
+                    *n
+                } else {
+                    acc
+                }
+            }),
+            "floor" => floor(first),
+            "abs" => {
+                if first < 0.0 {
+                    -first
+                } else {
+                    first
+                }
+            }
+            _ => return None,
+        };
+        Some(RuntimeValue::Number(result))
+    }
+}
+
+// no_std では f64::floor が使えないので、整数への変換を使って切り捨てる
+fn floor(x: f64) -> f64 {
+    // 2^53 以上の値はすでに整数なので、そのまま返す
+    if !x.is_finite() || x >= 9007199254740992.0 || x <= -9007199254740992.0 {
+        return x;
+    }
+
+    let truncated = x as i64 as f64;
+    if truncated > x {
+        truncated - 1.0
+    } else {
+        truncated
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum RuntimeValue {
-    Number(u64),
+    Number(f64),
+    Boolean(bool),
+    String(String),
+    Undefined, // 値が存在しないことを表す
+    Null,      // 意図的に値がないことを表す
+}
+
+impl RuntimeValue {
+    // 真偽値は true を 1、false を 0 として扱う
+    // 数値として解釈できない文字列は 0 とする
+    // undefined は NaN、null は 0 になる
+    fn to_number(&self) -> f64 {
+        match self {
+            RuntimeValue::Number(n) => *n,
+            RuntimeValue::Boolean(b) => *b as u8 as f64,
+            // 空白だけの文字列は 0、数値として読めない文字列は NaN になる
+            RuntimeValue::String(s) => match s.trim() {
+                "" => 0.0,
+                s => s.parse().unwrap_or(f64::NAN),
+            },
+            RuntimeValue::Undefined => f64::NAN,
+            RuntimeValue::Null => 0.0,
+        }
+    }
+
+    // '==' による比較。undefined と null はお互いにだけ等しい
+    // 文字列どうしは内容で比べ、それ以外は数値に変換して比べる
+    fn loosely_equals(&self, other: &RuntimeValue) -> bool {
+        match (self, other) {
+            (
+                RuntimeValue::Undefined | RuntimeValue::Null,
+                RuntimeValue::Undefined | RuntimeValue::Null,
+            ) => true,
+            (RuntimeValue::Undefined | RuntimeValue::Null, _)
+            | (_, RuntimeValue::Undefined | RuntimeValue::Null) => false,
+            (RuntimeValue::String(a), RuntimeValue::String(b)) => a == b,
+            _ => self.to_number() == other.to_number(),
+        }
+    }
+
+    fn is_truthy(&self) -> bool {
+        match self {
+            RuntimeValue::Number(n) => *n != 0.0 && !n.is_nan(),
+            RuntimeValue::Boolean(b) => *b,
+            RuntimeValue::String(s) => !s.is_empty(),
+            RuntimeValue::Undefined | RuntimeValue::Null => false,
+        }
+    }
+}
+
+impl Display for RuntimeValue {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        match self {
+            RuntimeValue::Number(n) if n.is_nan() => write!(f, "NaN"),
+            RuntimeValue::Number(n) if n.is_infinite() => {
+                write!(f, "{}", if *n > 0.0 { "Infinity" } else { "-Infinity" })
+            }
+            // 整数の値は小数点なしで表示する。i64 に正確に収まる 2^53 未満に限る
+            RuntimeValue::Number(n)
+                if *n == floor(*n) && *n < 9007199254740992.0 && *n > -9007199254740992.0 =>
+            {
+                write!(f, "{}", *n as i64)
+            }
+            RuntimeValue::Number(n) => write!(f, "{}", n),
+            RuntimeValue::Boolean(b) => write!(f, "{}", b),
+            RuntimeValue::String(s) => write!(f, "{}", s),
+            RuntimeValue::Undefined => write!(f, "undefined"),
+            RuntimeValue::Null => write!(f, "null"),
+        }
+    }
 }
 
 impl Add<RuntimeValue> for RuntimeValue {
     type Output = RuntimeValue;
 
+    // どちらかが文字列の場合は文字列として連結する
     fn add(self, rhs: RuntimeValue) -> Self::Output {
-        let (RuntimeValue::Number(lhs), RuntimeValue::Number(rhs)) = (&self, &rhs);
-        return RuntimeValue::Number(lhs + rhs);
+        if matches!(self, RuntimeValue::String(_)) || matches!(rhs, RuntimeValue::String(_)) {
+            return RuntimeValue::String(format!("{}{}", self, rhs));
+        }
+        return RuntimeValue::Number(self.to_number() + rhs.to_number());
     }
 }
 
@@ -86,8 +524,7 @@ impl Sub<RuntimeValue> for RuntimeValue {
     type Output = RuntimeValue;
 
     fn sub(self, rhs: RuntimeValue) -> Self::Output {
-        let (RuntimeValue::Number(lhs), RuntimeValue::Number(rhs)) = (&self, &rhs);
-        return RuntimeValue::Number(lhs - rhs);
+        return RuntimeValue::Number(self.to_number() - rhs.to_number());
     }
 }
 
@@ -104,7 +541,7 @@ mod tests {
         let mut parser = JsParser::new(lexer);
         let ast = parser.parse_ast();
         let mut runtime = JsRuntime::new();
-        let expected = [Some(RuntimeValue::Number(42))];
+        let expected = [Some(RuntimeValue::Number(42.0))];
 
         let mut i = 0;
         for node in ast.body() {
@@ -121,7 +558,7 @@ mod tests {
         let mut parser = JsParser::new(lexer);
         let ast = parser.parse_ast();
         let mut runtime = JsRuntime::new();
-        let expected = [Some(RuntimeValue::Number(3))];
+        let expected = [Some(RuntimeValue::Number(3.0))];
 
         let mut i = 0;
         for node in ast.body() {
@@ -138,7 +575,7 @@ mod tests {
         let mut parser = JsParser::new(lexer);
         let ast = parser.parse_ast();
         let mut runtime = JsRuntime::new();
-        let expected = [Some(RuntimeValue::Number(1))];
+        let expected = [Some(RuntimeValue::Number(1.0))];
 
         let mut i = 0;
         for node in ast.body() {
@@ -147,4 +584,370 @@ mod tests {
             i += 1;
         }
     }
+
+    #[test]
+    fn test_for_statement() {
+        let input = "var s = 0; for (var i = 0; i < 4; i = i + 1) { s = s + i; } s".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let ast = parser.parse_ast();
+        let mut runtime = JsRuntime::new();
+        let expected = [None, None, Some(RuntimeValue::Number(6.0))];
+
+        for (i, node) in ast.body().iter().enumerate() {
+            let result = runtime.evaluate(&Some(node.clone()));
+            assert_eq!(result, expected[i]);
+        }
+    }
+
+    #[test]
+    fn test_for_statement_iteration_cap() {
+        let input = "var n = 0; for (;;) { n = n + 1; } n".to_string();
+        let lexer = JsLexer::new(input);
+        let mut parser = JsParser::new(lexer);
+        let ast = parser.parse_ast();
+        let mut runtime = JsRuntime::new();
+        runtime.execute(&ast);
+
+        assert_eq!(
+            Some(RuntimeValue::Number(MAX_LOOP_ITERATIONS as f64)),
+            RefCell::borrow(&runtime.env()).get_variable("n")
+        );
+    }
+
+    #[test]
+    fn test_variable_declaration() {
+        let input = "var x = 1; var y = x + 2;".to_string();
+        let mut parser = JsParser::new(JsLexer::new(input));
+        let ast = parser.parse_ast();
+        let mut runtime = JsRuntime::new();
+        runtime.execute(&ast);
+
+        let env = runtime.env();
+        assert_eq!(
+            Some(RuntimeValue::Number(1.0)),
+            RefCell::borrow(&env).get_variable("x")
+        );
+        assert_eq!(
+            Some(RuntimeValue::Number(3.0)),
+            RefCell::borrow(&env).get_variable("y")
+        );
+        assert_eq!(None, RefCell::borrow(&env).get_variable("z"));
+    }
+
+    #[test]
+    fn test_assignment() {
+        let input = "var x = 0; x = 1 + 2; a = b = x;".to_string();
+        let mut parser = JsParser::new(JsLexer::new(input));
+        let ast = parser.parse_ast();
+        let mut runtime = JsRuntime::new();
+        runtime.execute(&ast);
+
+        // 代入は右から結合し、宣言されていない変数にも代入できる
+        let env = runtime.env();
+        for name in ["x", "a", "b"] {
+            assert_eq!(
+                Some(RuntimeValue::Number(3.0)),
+                RefCell::borrow(&env).get_variable(name)
+            );
+        }
+        assert_eq!(
+            Some(RuntimeValue::Number(5.0)),
+            evaluate_last("var x; x = 5")
+        );
+    }
+
+    #[test]
+    fn test_unbound_identifier() {
+        assert_eq!(None, evaluate_last("foo"));
+        assert_eq!(
+            Some(RuntimeValue::Number(1.0)),
+            evaluate_last("var foo = 1; foo")
+        );
+    }
+
+    #[test]
+    fn test_comparison() {
+        assert_eq!(Some(RuntimeValue::Boolean(true)), evaluate_last("1 < 2"));
+        assert_eq!(Some(RuntimeValue::Boolean(false)), evaluate_last("1 > 2"));
+        assert_eq!(Some(RuntimeValue::Boolean(true)), evaluate_last("3 == 3"));
+        assert_eq!(Some(RuntimeValue::Boolean(false)), evaluate_last("3 != 3"));
+        assert_eq!(
+            Some(RuntimeValue::Boolean(true)),
+            evaluate_last("\"3\" == 3")
+        );
+        assert_eq!(
+            Some(RuntimeValue::Boolean(true)),
+            evaluate_last("null == undefined")
+        );
+        assert_eq!(
+            Some(RuntimeValue::Boolean(false)),
+            evaluate_last("null == 0")
+        );
+        assert_eq!(
+            Some(RuntimeValue::Boolean(true)),
+            evaluate_last("1 < 2 == 2 > 1")
+        );
+    }
+
+    #[test]
+    fn test_string_to_number() {
+        assert_eq!(
+            Some(RuntimeValue::Boolean(false)),
+            evaluate_last("\"abc\" == 0")
+        );
+        assert_eq!(
+            Some(RuntimeValue::Boolean(true)),
+            evaluate_last("\" \" == 0")
+        );
+        match evaluate_last("7 - \"a\"") {
+            Some(RuntimeValue::Number(n)) => assert!(n.is_nan()),
+            other => panic!("expected NaN, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_if_statement() {
+        let run = |input: &str| {
+            let mut parser = JsParser::new(JsLexer::new(input.to_string()));
+            let ast = parser.parse_ast();
+            let mut runtime = JsRuntime::new();
+            runtime.execute(&ast);
+            let value = RefCell::borrow(&runtime.env()).get_variable("x");
+            value
+        };
+
+        assert_eq!(
+            Some(RuntimeValue::Number(5.0)),
+            run("var x = 0; if (1 < 2) x = 5;")
+        );
+        assert_eq!(
+            Some(RuntimeValue::Number(0.0)),
+            run("var x = 0; if (2 < 1) x = 5;")
+        );
+        assert_eq!(
+            Some(RuntimeValue::Number(7.0)),
+            run("var x = 0; if (0) { x = 5; } else { x = 7; }")
+        );
+        assert_eq!(
+            Some(RuntimeValue::Number(2.0)),
+            run("var x = 0; if (0) x = 1; else if (1) x = 2; else x = 3;")
+        );
+    }
+
+    #[test]
+    fn test_console_log() {
+        let input = "console.log(1 + 2); console.log(\"a\", 0.5, null);".to_string();
+        let mut parser = JsParser::new(JsLexer::new(input));
+        let ast = parser.parse_ast();
+        let mut runtime = JsRuntime::new();
+        runtime.execute(&ast);
+
+        assert_eq!(
+            ["3".to_string(), "a 0.5 null".to_string()].as_slice(),
+            runtime.logs()
+        );
+    }
+
+    #[test]
+    fn test_run_source() {
+        let mut runtime = JsRuntime::new();
+        assert_eq!(
+            Some(RuntimeValue::Number(3.0)),
+            runtime.run_source("1 + 2".to_string())
+        );
+        // 最後の式文の値を返し、その後の宣言は値を持たない
+        assert_eq!(
+            Some(RuntimeValue::Number(4.0)),
+            runtime.run_source("var a = 4; a; var b = 5;".to_string())
+        );
+        assert_eq!(None, runtime.run_source("var c = 6;".to_string()));
+        assert_eq!(
+            Some(RuntimeValue::Number(15.0)),
+            runtime.run_source("a + b + c".to_string())
+        );
+    }
+
+    fn evaluate_last(input: &str) -> Option<RuntimeValue> {
+        let lexer = JsLexer::new(input.to_string());
+        let mut parser = JsParser::new(lexer);
+        let ast = parser.parse_ast();
+        let mut runtime = JsRuntime::new();
+
+        let mut result = None;
+        for node in ast.body() {
+            result = runtime.evaluate(&Some(node.clone()));
+        }
+        result
+    }
+
+    #[test]
+    fn test_string_length() {
+        assert_eq!(
+            Some(RuntimeValue::Number(5.0)),
+            evaluate_last("\"hello\".length")
+        );
+    }
+
+    #[test]
+    fn test_string_char_at() {
+        assert_eq!(
+            Some(RuntimeValue::String("e".to_string())),
+            evaluate_last("\"hello\".charAt(1)")
+        );
+        assert_eq!(
+            Some(RuntimeValue::String("".to_string())),
+            evaluate_last("\"hello\".charAt(10)")
+        );
+    }
+
+    #[test]
+    fn test_string_index_of() {
+        assert_eq!(
+            Some(RuntimeValue::Number(2.0)),
+            evaluate_last("\"hello\".indexOf(\"l\")")
+        );
+        assert_eq!(
+            Some(RuntimeValue::Number(-1.0)),
+            evaluate_last("var s = \"hello\"; s.indexOf(\"z\")")
+        );
+    }
+
+    #[test]
+    fn test_math() {
+        assert_eq!(
+            Some(RuntimeValue::Number(5.0)),
+            evaluate_last("Math.max(1, 5, 3)")
+        );
+        assert_eq!(
+            Some(RuntimeValue::Number(1.0)),
+            evaluate_last("Math.min(1, 5, 3)")
+        );
+        assert_eq!(
+            Some(RuntimeValue::Number(3.0)),
+            evaluate_last("Math.floor(3.9)")
+        );
+        assert_eq!(
+            Some(RuntimeValue::Number(2.5)),
+            evaluate_last("Math.abs(0 - 2.5)")
+        );
+        assert_eq!(
+            Some(RuntimeValue::Number(f64::NEG_INFINITY)),
+            evaluate_last("Math.max()")
+        );
+    }
+
+    #[test]
+    fn test_display_number() {
+        assert_eq!("3", RuntimeValue::Number(3.0).to_string());
+        assert_eq!("-2", RuntimeValue::Number(-2.0).to_string());
+        assert_eq!("3.5", RuntimeValue::Number(3.5).to_string());
+        assert_eq!(
+            "100000000000000000000",
+            RuntimeValue::Number(1e20).to_string()
+        );
+        assert_eq!("NaN", RuntimeValue::Number(f64::NAN).to_string());
+    }
+
+    #[test]
+    fn test_string_concatenation() {
+        assert_eq!(
+            Some(RuntimeValue::String("ab".to_string())),
+            evaluate_last("\"a\" + \"b\"")
+        );
+        assert_eq!(
+            Some(RuntimeValue::String("n=5".to_string())),
+            evaluate_last("\"n=\" + 5")
+        );
+        // 左から順に評価するので、文字列より前の数値は先に足される
+        assert_eq!(
+            Some(RuntimeValue::String("3x".to_string())),
+            evaluate_last("1 + 2 + \"x\"")
+        );
+        assert_eq!(Some(RuntimeValue::Number(3.0)), evaluate_last("1 + 2"));
+    }
+
+    #[test]
+    fn test_number_to_string() {
+        assert_eq!(
+            Some("3".to_string()),
+            evaluate_last("3").map(|v| v.to_string())
+        );
+        assert_eq!(
+            Some("0.5".to_string()),
+            evaluate_last("0.5").map(|v| v.to_string())
+        );
+    }
+
+    #[test]
+    fn test_float_arithmetic() {
+        assert_eq!(Some(RuntimeValue::Number(3.5)), evaluate_last("1.5 + 2"));
+        assert_eq!(
+            Some(RuntimeValue::Number(-0.25)),
+            evaluate_last("1.25 - 1.5")
+        );
+        // 整数になる結果は小数点なしで表示する
+        assert_eq!(
+            Some("1".to_string()),
+            evaluate_last("0.5 + 0.5").map(|v| v.to_string())
+        );
+        assert_eq!(
+            Some("123456789000".to_string()),
+            evaluate_last("123456789 * 1000").map(|v| v.to_string())
+        );
+    }
+
+    #[test]
+    fn test_multiplicative() {
+        assert_eq!(Some(RuntimeValue::Number(7.0)), evaluate_last("1 + 2 * 3"));
+        assert_eq!(Some(RuntimeValue::Number(14.0)), evaluate_last("2 + 3 * 4"));
+        assert_eq!(Some(RuntimeValue::Number(5.0)), evaluate_last("10 / 2"));
+        assert_eq!(Some(RuntimeValue::Number(2.5)), evaluate_last("5 / 2"));
+        // 同じ優先度の演算子は左から結合する
+        assert_eq!(Some(RuntimeValue::Number(3.0)), evaluate_last("12 / 2 / 2"));
+        assert_eq!(
+            Some(RuntimeValue::Number(f64::INFINITY)),
+            evaluate_last("1 / 0")
+        );
+    }
+
+    #[test]
+    fn test_evaluate_program() {
+        let mut runtime = JsRuntime::new();
+        let mut run = |src: &str| {
+            let program = JsParser::new(JsLexer::new(src.to_string())).parse_ast();
+            runtime.evaluate_program(&program)
+        };
+
+        assert_eq!(Ok(RuntimeValue::Undefined), run("var x = 10;"));
+        assert_eq!(Ok(RuntimeValue::Number(20.0)), run("x * 2"));
+        assert_eq!(Ok(RuntimeValue::Undefined), run(""));
+        assert!(run("y").is_err());
+    }
+
+    #[test]
+    fn test_undefined() {
+        assert_eq!(Some(RuntimeValue::Undefined), evaluate_last("var x; x"));
+        assert_eq!(
+            Some(RuntimeValue::Undefined),
+            evaluate_last("\"hello\".missing")
+        );
+        assert_eq!(Some(RuntimeValue::Undefined), evaluate_last("undefined"));
+        assert_eq!(
+            Some("undefined".to_string()),
+            evaluate_last("var x; x").map(|v| v.to_string())
+        );
+    }
+
+    #[test]
+    fn test_null() {
+        assert_eq!(Some(RuntimeValue::Null), evaluate_last("null"));
+        assert_eq!(Some(RuntimeValue::Null), evaluate_last("var x = null; x"));
+        assert_eq!(
+            Some("null".to_string()),
+            evaluate_last("null").map(|v| v.to_string())
+        );
+        assert!(!RuntimeValue::Null.is_truthy());
+        assert!(!RuntimeValue::Undefined.is_truthy());
+    }
 }
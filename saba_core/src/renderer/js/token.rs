@@ -3,12 +3,15 @@ use alloc::{
     vec::Vec,
 };
 
-static RESERVED_WORDS: [&str; 1] = ["var"];
+static RESERVED_WORDS: [&str; 5] = ["var", "for", "null", "if", "else"];
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+static OPERATORS: [&str; 2] = ["==", "!="];
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Token {
     Punctuator(char),
-    Number(u64),
+    Operator(String), // "==" のように複数の文字からなる演算子
+    Number(f64),
     Identifier(String),
     Keyword(String),
     StringLiteral(String),
@@ -27,41 +30,58 @@ impl JsLexer {
         }
     }
 
-    fn consume_number(&mut self) -> u64 {
-        let mut num = 0;
+    // 整数部と、'.' の後に数字が続く場合は小数部を読む
+    fn consume_number(&mut self) -> f64 {
+        let mut digits = String::new();
 
-        loop {
-            if self.pos >= self.input.len() {
-                return num;
+        while let Some(c) = self.input.get(self.pos) {
+            if !c.is_ascii_digit() {
+                break;
             }
+            digits.push(*c);
+            self.pos += 1;
+        }
 
-            let c = self.input[self.pos];
-
-            match c {
-                '0'..='9' => {
-                    num = num * 10 + (c.to_digit(10).unwrap() as u64);
-                    self.pos += 1;
+        let has_fraction = self.input.get(self.pos) == Some(&'.')
+            && self
+                .input
+                .get(self.pos + 1)
+                .map_or(false, |c| c.is_ascii_digit());
+        if has_fraction {
+            // '.' を消費する
+            digits.push('.');
+            self.pos += 1;
+            while let Some(c) = self.input.get(self.pos) {
+                if !c.is_ascii_digit() {
+                    break;
                 }
-                _ => break,
+                digits.push(*c);
+                self.pos += 1;
             }
         }
 
-        return num;
+        // 1桁ずつ掛け算や足し算をすると丸め誤差がたまるので、まとめて f64 に変換する
+        digits.parse::<f64>().unwrap_or(0.0)
     }
 
     fn contains(&self, keyword: &str) -> bool {
         for i in 0..keyword.len() {
-            if keyword
-                .chars()
-                .nth(i)
-                .expect("failed to access to i-th char")
-                != self.input[self.pos + i]
+            if Some(
+                keyword
+                    .chars()
+                    .nth(i)
+                    .expect("failed to access to i-th char"),
+            ) != self.input.get(self.pos + i).copied()
             {
                 return false;
             }
         }
 
-        true
+        // "format" のように予約語で始まる識別子は予約語として扱わない
+        match self.input.get(self.pos + keyword.len()) {
+            Some(c) => !(c.is_ascii_alphanumeric() || *c == '_' || *c == '$'),
+            None => true,
+        }
     }
 
     fn consume_identifier(&mut self) -> String {
@@ -72,7 +92,10 @@ impl JsLexer {
                 return result;
             }
 
-            if self.input[self.pos].is_ascii_alphanumeric() || self.input[self.pos] == '$' {
+            if self.input[self.pos].is_ascii_alphanumeric()
+                || self.input[self.pos] == '_'
+                || self.input[self.pos] == '$'
+            {
                 result.push(self.input[self.pos]);
                 self.pos += 1;
             } else {
@@ -81,8 +104,10 @@ impl JsLexer {
         }
     }
 
+    // 開始の引用符と同じ引用符が現れるまでを文字列として読む
     fn consume_string(&mut self) -> String {
         let mut result = String::new();
+        let quote = self.input[self.pos];
         self.pos += 1;
 
         loop {
@@ -90,7 +115,7 @@ impl JsLexer {
                 return result;
             }
 
-            if self.input[self.pos] == '"' {
+            if self.input[self.pos] == quote {
                 self.pos += 1;
                 return result;
             }
@@ -100,6 +125,21 @@ impl JsLexer {
         }
     }
 
+    // 複数の文字からなる演算子。1文字の句読点より先に確認する
+    fn check_operator(&self) -> Option<String> {
+        for operator in OPERATORS {
+            let matched = operator
+                .chars()
+                .enumerate()
+                .all(|(i, c)| self.input.get(self.pos + i) == Some(&c));
+            if matched {
+                return Some(operator.to_string());
+            }
+        }
+
+        None
+    }
+
     fn check_reserved_word(&self) -> Option<String> {
         for word in RESERVED_WORDS {
             if self.contains(word) {
@@ -134,16 +174,21 @@ impl Iterator for JsLexer {
             return token;
         }
 
+        if let Some(operator) = self.check_operator() {
+            self.pos += operator.len();
+            return Some(Token::Operator(operator));
+        }
+
         let c = self.input[self.pos];
         let token = match c {
             'a'..='z' | 'A'..='Z' | '_' | '$' => Token::Identifier(self.consume_identifier()),
-            '+' | '-' | ';' | '=' | '.' | ',' | '(' | ')' | '{' | '}' => {
+            '+' | '-' | '*' | '/' | ';' | '=' | '.' | ',' | '(' | ')' | '{' | '}' | '<' | '>' => {
                 let t = Token::Punctuator(c);
                 self.pos += 1;
                 t
             }
             '0'..='9' => Token::Number(self.consume_number()),
-            '"' => Token::StringLiteral(self.consume_string()),
+            '"' | '\'' => Token::StringLiteral(self.consume_string()),
             _ => unimplemented!("char {:?} is not implemented yet", c),
         };
 
@@ -170,7 +215,7 @@ mod tests {
         let input = "42".to_string();
         let mut lexer = JsLexer::new(input).peekable();
 
-        let expected = [Token::Number(42)].to_vec();
+        let expected = [Token::Number(42.0)].to_vec();
         let mut i = 0;
         while lexer.peek().is_some() {
             assert_eq!(Some(expected[i].clone()), lexer.next());
@@ -184,7 +229,12 @@ mod tests {
         let input = "1 + 2".to_string();
         let mut lexer = JsLexer::new(input).peekable();
 
-        let expected = [Token::Number(1), Token::Punctuator('+'), Token::Number(2)].to_vec();
+        let expected = [
+            Token::Number(1.0),
+            Token::Punctuator('+'),
+            Token::Number(2.0),
+        ]
+        .to_vec();
         let mut i = 0;
         while lexer.peek().is_some() {
             assert_eq!(Some(expected[i].clone()), lexer.next());
@@ -222,14 +272,14 @@ mod tests {
             Token::Keyword("var".to_string()),
             Token::Identifier("foo".to_string()),
             Token::Punctuator('='),
-            Token::Number(42),
+            Token::Number(42.0),
             Token::Punctuator(';'),
             Token::Keyword("var".to_string()),
             Token::Identifier("result".to_string()),
             Token::Punctuator('='),
             Token::Identifier("foo".to_string()),
             Token::Punctuator('+'),
-            Token::Number(1),
+            Token::Number(1.0),
             Token::Punctuator(';'),
         ]
         .to_vec();
@@ -241,4 +291,104 @@ mod tests {
         }
         assert!(lexer.peek().is_none());
     }
+
+    #[test]
+    fn test_string_literal() {
+        let input = "\"hi\" 'it\"s' \"a'b\"".to_string();
+        let lexer = JsLexer::new(input);
+        let expected = [
+            Token::StringLiteral("hi".to_string()),
+            Token::StringLiteral("it\"s".to_string()),
+            Token::StringLiteral("a'b".to_string()),
+        ]
+        .to_vec();
+
+        assert_eq!(expected, lexer.collect::<Vec<Token>>());
+    }
+
+    #[test]
+    fn test_identifier() {
+        let input = "foo _bar $baz var variable".to_string();
+        let lexer = JsLexer::new(input);
+        let expected = [
+            Token::Identifier("foo".to_string()),
+            Token::Identifier("_bar".to_string()),
+            Token::Identifier("$baz".to_string()),
+            Token::Keyword("var".to_string()),
+            Token::Identifier("variable".to_string()),
+        ]
+        .to_vec();
+
+        assert_eq!(expected, lexer.collect::<Vec<Token>>());
+    }
+
+    #[test]
+    fn test_equality_operators() {
+        let input = "a == 1 != b = 2".to_string();
+        let lexer = JsLexer::new(input);
+        let expected = [
+            Token::Identifier("a".to_string()),
+            Token::Operator("==".to_string()),
+            Token::Number(1.0),
+            Token::Operator("!=".to_string()),
+            Token::Identifier("b".to_string()),
+            Token::Punctuator('='),
+            Token::Number(2.0),
+        ]
+        .to_vec();
+
+        assert_eq!(expected, lexer.collect::<Vec<Token>>());
+    }
+
+    #[test]
+    fn test_for_keyword() {
+        let input = "for (i = 0; i < 4;) format".to_string();
+        let lexer = JsLexer::new(input);
+        let expected = [
+            Token::Keyword("for".to_string()),
+            Token::Punctuator('('),
+            Token::Identifier("i".to_string()),
+            Token::Punctuator('='),
+            Token::Number(0.0),
+            Token::Punctuator(';'),
+            Token::Identifier("i".to_string()),
+            Token::Punctuator('<'),
+            Token::Number(4.0),
+            Token::Punctuator(';'),
+            Token::Punctuator(')'),
+            Token::Identifier("format".to_string()),
+        ]
+        .to_vec();
+
+        assert_eq!(expected, lexer.collect::<Vec<Token>>());
+    }
+
+    #[test]
+    fn test_decimal_number() {
+        let input = "3.9 1.length".to_string();
+        let lexer = JsLexer::new(input);
+        let expected = [
+            Token::Number(3.9),
+            Token::Number(1.0),
+            Token::Punctuator('.'),
+            Token::Identifier("length".to_string()),
+        ]
+        .to_vec();
+
+        assert_eq!(expected, lexer.collect::<Vec<Token>>());
+    }
+
+    #[test]
+    fn test_decimal_number_is_rounded_correctly() {
+        let input = "0.3 1.1 123.456".to_string();
+        let lexer = JsLexer::new(input);
+        let expected = [
+            Token::Number(0.3),
+            Token::Number(1.1),
+            Token::Number(123.456),
+        ]
+        .to_vec();
+
+        assert_eq!(expected, lexer.collect::<Vec<Token>>());
+    }
 }
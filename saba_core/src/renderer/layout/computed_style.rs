@@ -1,9 +1,11 @@
+use crate::constants::{CHAR_HEIGHT, TAB_SIZE};
 use crate::error::Error;
 use crate::renderer::dom::node::{ElementKind, Node, NodeKind};
 use alloc::format;
 use alloc::rc::Rc;
 use alloc::string::{String, ToString};
 use core::cell::RefCell;
+use core::str::FromStr;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct ComputedStyle {
@@ -11,7 +13,10 @@ pub struct ComputedStyle {
     color: Option<Color>,
     display: Option<DisplayType>,
     font_size: Option<FontSize>,
+    font_family: Option<FontFamily>,
     text_decoration: Option<TextDecoration>,
+    white_space: Option<WhiteSpace>,
+    tab_size: Option<usize>,
     height: Option<f64>,
     width: Option<f64>,
 }
@@ -23,7 +28,10 @@ impl ComputedStyle {
             color: None,
             display: None,
             font_size: None,
+            font_family: None,
             text_decoration: None,
+            white_space: None,
+            tab_size: None,
             height: None,
             width: None,
         }
@@ -41,11 +49,20 @@ impl ComputedStyle {
             if self.font_size.is_none() && parent_style.font_size() != FontSize::Medium {
                 self.font_size = Some(parent_style.font_size());
             }
+            if self.font_family.is_none() && parent_style.font_family() != FontFamily::Serif {
+                self.font_family = Some(parent_style.font_family());
+            }
             if self.text_decoration.is_none()
                 && parent_style.text_decoration() != TextDecoration::None
             {
                 self.text_decoration = Some(parent_style.text_decoration());
             }
+            if self.white_space.is_none() && parent_style.white_space() != WhiteSpace::Normal {
+                self.white_space = Some(parent_style.white_space());
+            }
+            if self.tab_size.is_none() && parent_style.tab_size() != TAB_SIZE {
+                self.tab_size = Some(parent_style.tab_size());
+            }
         }
 
         if self.background_color.is_none() {
@@ -60,9 +77,18 @@ impl ComputedStyle {
         if self.font_size.is_none() {
             self.font_size = Some(FontSize::default(node));
         }
+        if self.font_family.is_none() {
+            self.font_family = Some(FontFamily::default(node));
+        }
         if self.text_decoration.is_none() {
             self.text_decoration = Some(TextDecoration::default(node));
         }
+        if self.white_space.is_none() {
+            self.white_space = Some(WhiteSpace::default(node));
+        }
+        if self.tab_size.is_none() {
+            self.tab_size = Some(TAB_SIZE);
+        }
         if self.height.is_none() {
             self.height = Some(0.0);
         }
@@ -107,11 +133,42 @@ impl ComputedStyle {
             .expect("failed to access CSS property: font_size")
     }
 
+    pub fn set_font_size(&mut self, font_size: FontSize) {
+        self.font_size = Some(font_size);
+    }
+
+    pub fn set_font_family(&mut self, font_family: FontFamily) {
+        self.font_family = Some(font_family);
+    }
+
+    pub fn font_family(&self) -> FontFamily {
+        self.font_family
+            .expect("failed to access CSS property: font_family")
+    }
+
     pub fn text_decoration(&self) -> TextDecoration {
         self.text_decoration
             .expect("failed to access CSS property: text_decoration")
     }
 
+    pub fn set_white_space(&mut self, white_space: WhiteSpace) {
+        self.white_space = Some(white_space);
+    }
+
+    pub fn white_space(&self) -> WhiteSpace {
+        self.white_space
+            .expect("failed to access CSS property: white_space")
+    }
+
+    pub fn set_tab_size(&mut self, tab_size: usize) {
+        self.tab_size = Some(tab_size);
+    }
+
+    pub fn tab_size(&self) -> usize {
+        self.tab_size
+            .expect("failed to access CSS property: tab_size")
+    }
+
     pub fn set_height(&mut self, height: f64) {
         self.height = Some(height)
     }
@@ -212,6 +269,38 @@ impl Color {
         })
     }
 
+    // "#rgb" と "#rrggbb" の形式を受け付ける。先頭の '#' は省略できる
+    // 名前のない色も表せるので、from_code と違って任意の色を扱える
+    pub fn from_hex(hex: &str) -> Result<Self, Error> {
+        let digits = hex.strip_prefix('#').unwrap_or(hex);
+        if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(Error::UnexpectedInput(format!(
+                "invalid color code {}",
+                hex
+            )));
+        }
+
+        let code = match digits.len() {
+            // 3桁の形式は各桁を2つ並べて6桁に展開する
+            3 => digits.chars().fold(String::from("#"), |mut code, c| {
+                code.push(c);
+                code.push(c);
+                code
+            }),
+            6 => format!("#{}", digits),
+            _ => {
+                return Err(Error::UnexpectedInput(format!(
+                    "invalid color code {}",
+                    hex
+                )))
+            }
+        }
+        .to_ascii_lowercase();
+
+        let name = Color::from_code(&code).ok().and_then(|c| c.name);
+        Ok(Self { name, code })
+    }
+
     pub fn white() -> Self {
         Self {
             name: Some("white".to_string()),
@@ -226,19 +315,58 @@ impl Color {
         }
     }
 
+    // 明暗を反転した色。各成分を 0xff から引く
+    pub fn inverted(&self) -> Self {
+        let code = format!("#{:06x}", 0xffffff - self.code_u32());
+        let name = Color::from_code(&code).ok().and_then(|c| c.name);
+        Self { name, code }
+    }
+
     pub fn code_u32(&self) -> u32 {
         u32::from_str_radix(self.code.trim_start_matches('#'), 16).unwrap()
     }
+
+    pub fn r(&self) -> u8 {
+        (self.code_u32() >> 16) as u8
+    }
+
+    pub fn g(&self) -> u8 {
+        (self.code_u32() >> 8) as u8
+    }
+
+    pub fn b(&self) -> u8 {
+        self.code_u32() as u8
+    }
+
+    // 透明度を表す形式にはまだ対応していないので、常に不透明
+    pub fn a(&self) -> u8 {
+        0xff
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum FontSize {
+    Small,
     Medium,
+    Large,
     XLarge,
     XXLarge,
+    Px(i64), // ピクセル単位で指定された大きさ
 }
 
 impl FontSize {
+    // 文字の高さをピクセル単位で返す。medium は CHAR_HEIGHT と同じ大きさ
+    pub fn px(&self) -> i64 {
+        match self {
+            FontSize::Small => CHAR_HEIGHT * 13 / 16,
+            FontSize::Medium => CHAR_HEIGHT,
+            FontSize::Large => CHAR_HEIGHT * 9 / 8,
+            FontSize::XLarge => CHAR_HEIGHT * 2,
+            FontSize::XXLarge => CHAR_HEIGHT * 3,
+            FontSize::Px(px) => *px,
+        }
+    }
+
     fn default(node: &Rc<RefCell<Node>>) -> Self {
         match &node.borrow().kind() {
             NodeKind::Element(element) => match element.kind() {
@@ -251,6 +379,61 @@ impl FontSize {
     }
 }
 
+// font-size のキーワード
+impl FromStr for FontSize {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "small" => Ok(FontSize::Small),
+            "medium" => Ok(FontSize::Medium),
+            "large" => Ok(FontSize::Large),
+            "x-large" => Ok(FontSize::XLarge),
+            "xx-large" => Ok(FontSize::XXLarge),
+            _ => Err(Error::UnexpectedInput(format!(
+                "font-size {:?} is not supported yet",
+                s
+            ))),
+        }
+    }
+}
+
+// 総称フォントファミリー
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum FontFamily {
+    Serif,
+    SansSerif,
+    Monospace,
+}
+
+impl FontFamily {
+    fn default(node: &Rc<RefCell<Node>>) -> Self {
+        match &node.borrow().kind() {
+            NodeKind::Element(element) => match element.kind() {
+                ElementKind::Code | ElementKind::Pre => FontFamily::Monospace,
+                _ => FontFamily::Serif,
+            },
+            _ => FontFamily::Serif,
+        }
+    }
+}
+
+impl FromStr for FontFamily {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "serif" => Ok(Self::Serif),
+            "sans-serif" => Ok(Self::SansSerif),
+            "monospace" => Ok(Self::Monospace),
+            _ => Err(Error::UnexpectedInput(format!(
+                "font-family {:?} is not supported yet",
+                s
+            ))),
+        }
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum DisplayType {
     Block,
@@ -303,3 +486,78 @@ impl TextDecoration {
         }
     }
 }
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum WhiteSpace {
+    Normal, // 連続する空白と改行をまとめる
+    Pre,    // 空白と改行をそのまま保持する
+}
+
+impl WhiteSpace {
+    fn default(node: &Rc<RefCell<Node>>) -> Self {
+        match &node.borrow().kind() {
+            NodeKind::Element(element) => match element.kind() {
+                ElementKind::Pre => WhiteSpace::Pre,
+                _ => WhiteSpace::Normal,
+            },
+            _ => WhiteSpace::Normal,
+        }
+    }
+}
+
+impl FromStr for WhiteSpace {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "normal" => Ok(Self::Normal),
+            "pre" => Ok(Self::Pre),
+            _ => Err(Error::UnexpectedInput(format!(
+                "white-space {:?} is not supported yet",
+                s
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_color_from_name() {
+        let red = Color::from_name("red").expect("red should be valid");
+        assert_eq!((0xff, 0, 0, 0xff), (red.r(), red.g(), red.b(), red.a()));
+        assert_eq!(0xff0000, red.code_u32());
+    }
+
+    #[test]
+    fn test_color_from_hex() {
+        let white = Color::from_hex("#fff").expect("#fff should be valid");
+        assert_eq!(Color::white(), white);
+        assert_eq!(crate::constants::WHITE, white.code_u32());
+
+        let color = Color::from_hex("#AABBCC").expect("#AABBCC should be valid");
+        assert_eq!(
+            (0xaa, 0xbb, 0xcc, 0xff),
+            (color.r(), color.g(), color.b(), color.a())
+        );
+    }
+
+    #[test]
+    fn test_font_size_px() {
+        assert_eq!(CHAR_HEIGHT, FontSize::Medium.px());
+        assert!(FontSize::Small.px() < FontSize::Medium.px());
+        assert!(FontSize::Large.px() > FontSize::Medium.px());
+        assert_eq!(32, FontSize::Px(32).px());
+        assert_eq!(Ok(FontSize::Large), FontSize::from_str("large"));
+        assert!(FontSize::from_str("huge").is_err());
+    }
+
+    #[test]
+    fn test_invalid_color() {
+        assert!(Color::from_name("not-a-color").is_err());
+        assert!(Color::from_hex("#ff").is_err());
+        assert!(Color::from_hex("#gggggg").is_err());
+    }
+}
@@ -1,17 +1,17 @@
-use crate::constants::{
-    CHAR_WIDTH, CHAR_WITH_PADDING, CONTENT_AREA_WIDTH, WINDOW_PADDING, WINDOW_WIDTH,
-};
+use crate::constants::{CHAR_HEIGHT, CHAR_WIDTH, CHAR_WITH_PADDING, CONTENT_AREA_WIDTH};
 use crate::display_item::DisplayItem;
 use crate::renderer::css::cssom::{ComponentValue, Declaration, Selector};
 use crate::renderer::dom::node::{Node, NodeKind};
-use crate::renderer::layout::computed_style::{Color, ComputedStyle, DisplayType, FontSize};
+use crate::renderer::layout::computed_style::{
+    Color, ComputedStyle, DisplayType, FontFamily, FontSize, WhiteSpace,
+};
 use alloc::rc::Rc;
 use alloc::rc::Weak;
 use alloc::string::String;
-use alloc::string::ToString;
 use alloc::vec;
 use alloc::vec::Vec;
 use core::cell::RefCell;
+use core::str::FromStr;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum LayoutObjectKind {
@@ -54,34 +54,7 @@ impl LayoutObject {
     }
 
     pub fn is_node_selected(&self, selector: &Selector) -> bool {
-        match &self.node_kind() {
-            NodeKind::Element(e) => match selector {
-                Selector::TypeSelector(type_name) => {
-                    if e.kind().to_string() == *type_name {
-                        return true;
-                    }
-                    false
-                }
-                Selector::ClassSelector(class_name) => {
-                    for attr in &e.attributes() {
-                        if attr.name() == "class" && attr.value() == *class_name {
-                            return true;
-                        }
-                    }
-                    false
-                }
-                Selector::IdSelector(id_name) => {
-                    for attr in &e.attributes() {
-                        if attr.name() == "id" && attr.value() == *id_name {
-                            return true;
-                        }
-                    }
-                    false
-                }
-                Selector::UnknownSelector => false,
-            },
-            _ => false,
-        }
+        is_selected(&self.node.borrow(), selector)
     }
 
     pub fn update_kind(&mut self) {
@@ -123,7 +96,7 @@ impl LayoutObject {
                     }
 
                     if let ComponentValue::HashToken(color_code) = &declaration.value {
-                        let color = match Color::from_code(&color_code) {
+                        let color = match Color::from_hex(color_code) {
                             Ok(color) => color,
                             Err(_) => Color::white(),
                         };
@@ -141,13 +114,44 @@ impl LayoutObject {
                     }
 
                     if let ComponentValue::HashToken(color_code) = &declaration.value {
-                        let color = match Color::from_code(&color_code) {
+                        let color = match Color::from_hex(color_code) {
                             Ok(color) => color,
                             Err(_) => Color::black(),
                         };
                         self.style.set_color(color);
                     }
                 }
+                "font-family" => {
+                    if let ComponentValue::Ident(value) = &declaration.value {
+                        if let Ok(font_family) = FontFamily::from_str(value) {
+                            self.style.set_font_family(font_family);
+                        }
+                    }
+                }
+                // 数値は px として扱う。0 以下の大きさは無視する
+                "font-size" => match &declaration.value {
+                    ComponentValue::Number(value) if *value > 0.0 => {
+                        self.style.set_font_size(FontSize::Px(*value as i64));
+                    }
+                    ComponentValue::Ident(value) => {
+                        if let Ok(font_size) = FontSize::from_str(value) {
+                            self.style.set_font_size(font_size);
+                        }
+                    }
+                    _ => {}
+                },
+                "white-space" => {
+                    if let ComponentValue::Ident(value) = &declaration.value {
+                        if let Ok(white_space) = WhiteSpace::from_str(value) {
+                            self.style.set_white_space(white_space);
+                        }
+                    }
+                }
+                "tab-size" => {
+                    if let ComponentValue::Number(value) = declaration.value {
+                        self.style.set_tab_size(value as usize);
+                    }
+                }
                 "display" => {
                     if let ComponentValue::Ident(value) = declaration.value {
                         let display_type = match DisplayType::from_str(&value) {
@@ -212,29 +216,21 @@ impl LayoutObject {
                 size.set_height(height);
             }
             LayoutObjectKind::Text => {
-                if let NodeKind::Text(t) = self.node_kind() {
-                    let ratio = match self.style.font_size() {
-                        FontSize::Medium => 1,
-                        FontSize::XLarge => 2,
-                        FontSize::XXLarge => 3,
-                    };
+                let char_width = self.char_width();
+                let lines = self.text_lines();
+                let max_len = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0);
 
-                    let width = CHAR_WIDTH * ratio * t.len() as i64;
-                    if width > CONTENT_AREA_WIDTH {
-                        size.set_width(CONTENT_AREA_WIDTH);
-                        let line_num = if width.wrapping_rem(CONTENT_AREA_WIDTH) == 0 {
-                            width.wrapping_div(CONTENT_AREA_WIDTH)
-                        } else {
-                            width.wrapping_div(CONTENT_AREA_WIDTH) + 1
-                        };
-                        size.set_height(CHAR_WITH_PADDING * ratio * line_num);
-                    } else {
-                        size.set_width(width);
-                        size.set_height(CHAR_WITH_PADDING * ratio);
-                    }
+                // 折り返した場合は内容領域の幅いっぱいを使う
+                if lines.len() > 1 && self.style.white_space() != WhiteSpace::Pre {
+                    size.set_width(CONTENT_AREA_WIDTH);
+                } else {
+                    size.set_width(char_width * max_len as i64);
                 }
+                size.set_height(self.line_height() * lines.len() as i64);
             }
         }
+
+        self.size = size;
     }
 
     pub fn compute_position(
@@ -292,42 +288,71 @@ impl LayoutObject {
                 // 描画するインライン要素がない
             }
             LayoutObjectKind::Text => {
-                if let NodeKind::Text(t) = self.node_kind() {
-                    let mut v = vec![];
-
-                    let ratio = match self.style.font_size() {
-                        FontSize::Medium => 1,
-                        FontSize::XLarge => 2,
-                        FontSize::XXLarge => 3,
-                    };
-                    let plain_text = t
-                        .replace("\n", "")
-                        .split(' ')
-                        .filter(|s| !s.is_empty())
-                        .collect::<Vec<_>>()
-                        .join(" ");
-                    let lines = split_text(plain_text, CHAR_WIDTH * ratio);
-                    let mut i = 0;
-                    for line in lines {
-                        let item = DisplayItem::Text {
-                            text: line,
-                            style: self.style(),
-                            layout_point: LayoutPoint::new(
-                                self.point().x(),
-                                self.point().y() + i * CHAR_WITH_PADDING,
-                            ),
-                        };
-                        v.push(item);
-                        i += 1;
-                    }
-                    return v;
-                }
+                return self
+                    .line_fragments()
+                    .into_iter()
+                    .map(|fragment| DisplayItem::Text {
+                        text: fragment.text,
+                        style: self.style(),
+                        layout_point: fragment.point,
+                    })
+                    .collect();
             }
         }
 
         vec![]
     }
 
+    // テキストを折り返した各行と、その行を描画する位置
+    // テキスト以外のレイアウトオブジェクトは行を持たない
+    pub fn line_fragments(&self) -> Vec<LineFragment> {
+        let mut y = self.point().y();
+        let mut fragments = Vec::new();
+        for line in self.text_lines() {
+            fragments.push(LineFragment {
+                text: line,
+                point: LayoutPoint::new(self.point().x(), y),
+            });
+            y += self.line_height();
+        }
+        fragments
+    }
+
+    // 空白を保持するテキストは折り返さず、改行ごとに1行とする
+    // それ以外のテキストは連続する空白をまとめ、内容領域の幅に収まるように空白の位置で折り返す
+    fn text_lines(&self) -> Vec<String> {
+        let t = match self.node_kind() {
+            NodeKind::Text(t) => t,
+            _ => return Vec::new(),
+        };
+
+        if self.style.white_space() == WhiteSpace::Pre {
+            return preformatted_lines(&t, self.style.tab_size());
+        }
+
+        let plain_text = t
+            .replace("\n", "")
+            .split(' ')
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+        split_text(plain_text, self.char_width())
+    }
+
+    // 文字の高さ（px）。font-size が指定されていなければ CHAR_HEIGHT
+    pub fn font_size(&self) -> i64 {
+        self.style.font_size().px()
+    }
+
+    // 文字の幅と行の高さは、CHAR_HEIGHT に対する文字の高さの比で拡大・縮小する
+    fn char_width(&self) -> i64 {
+        (CHAR_WIDTH * self.font_size() / CHAR_HEIGHT).max(1)
+    }
+
+    fn line_height(&self) -> i64 {
+        (CHAR_WITH_PADDING * self.font_size() / CHAR_HEIGHT).max(1)
+    }
+
     pub fn kind(&self) -> LayoutObjectKind {
         self.kind
     }
@@ -368,6 +393,13 @@ impl LayoutObject {
     }
 }
 
+// 折り返したテキストの1行分
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineFragment {
+    pub text: String,
+    pub point: LayoutPoint,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct LayoutPoint {
     x: i64,
@@ -430,26 +462,233 @@ impl PartialEq for LayoutObject {
     }
 }
 
-fn find_index_for_line_break(line: String, max_index: usize) -> usize {
-    for i in (0..max_index).rev() {
-        if line.chars().collect::<Vec<char>>()[i] == ' ' {
+// DOM ノードがセレクタにマッチするかどうか
+pub fn is_selected(node: &Node, selector: &Selector) -> bool {
+    match &node.kind() {
+        NodeKind::Element(e) => match selector {
+            Selector::TypeSelector(type_name) => {
+                if e.tag_name() == *type_name {
+                    return true;
+                }
+                false
+            }
+            Selector::ClassSelector(class_name) => e.class_list().contains(class_name),
+            Selector::UniversalSelector => true,
+            Selector::IdSelector(id_name) => e.get_attribute("id").as_ref() == Some(id_name),
+            // 親がドキュメントである要素（html 要素）がルート
+            Selector::RootSelector => match node.parent().upgrade() {
+                Some(parent) => parent.borrow().kind() == NodeKind::Document,
+                None => false,
+            },
+            Selector::NotSelector(inner) => !is_selected(node, inner),
+            Selector::CompoundSelector(selectors) => selectors.iter().all(|s| is_selected(node, s)),
+            Selector::UnknownSelector => false,
+        },
+        _ => false,
+    }
+}
+
+// タブを次のタブストップまでの空白に展開する
+pub fn expand_tabs(line: &str, tab_size: usize) -> String {
+    let mut result = String::new();
+    let mut column = 0;
+
+    for c in line.chars() {
+        if c == '\t' && tab_size > 0 {
+            let spaces = tab_size - column % tab_size;
+            result.push_str(&" ".repeat(spaces));
+            column += spaces;
+        } else {
+            result.push(c);
+            column += 1;
+        }
+    }
+
+    result
+}
+
+// white-space: pre のテキストを改行で行に分け、タブを展開する
+fn preformatted_lines(text: &str, tab_size: usize) -> Vec<String> {
+    text.trim_start_matches('\n')
+        .split('\n')
+        .map(|line| expand_tabs(line, tab_size))
+        .collect()
+}
+
+// max_index 文字までに収まる最後の空白の位置。空白がなければ max_index で区切る
+fn find_index_for_line_break(line: &[char], max_index: usize) -> usize {
+    for i in (1..=max_index).rev() {
+        if line[i] == ' ' {
             return i;
         }
     }
     max_index
 }
 
+// 1行が内容領域の幅に収まるように、文字単位で数えて折り返す
 fn split_text(line: String, char_width: i64) -> Vec<String> {
+    let max_chars = (CONTENT_AREA_WIDTH / char_width).max(1) as usize;
     let mut result: Vec<String> = vec![];
-    if line.len() as i64 * char_width > (WINDOW_WIDTH + WINDOW_PADDING) {
-        let s = line.split_at(find_index_for_line_break(
-            line.clone(),
-            ((WINDOW_WIDTH + WINDOW_PADDING) / char_width) as usize,
-        ));
-        result.push(s.0.to_string());
-        result.extend(split_text(s.1.trim().to_string(), char_width));
-    } else {
-        result.push(line);
+    let mut rest: Vec<char> = line.chars().collect();
+
+    while rest.len() > max_chars {
+        let index = find_index_for_line_break(&rest, max_chars);
+        result.push(rest[..index].iter().collect());
+        rest = rest[index..]
+            .iter()
+            .copied()
+            .skip_while(|c| *c == ' ')
+            .collect();
     }
+    result.push(rest.into_iter().collect());
     result
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::css::cssom::StyleSheet;
+    use crate::renderer::dom::api::get_target_element_node;
+    use crate::renderer::dom::node::ElementKind;
+    use crate::renderer::html::parser::HtmlParser;
+    use crate::renderer::html::token::HtmlTokenizer;
+    use crate::renderer::layout::layout_view::LayoutView;
+    use alloc::boxed::Box;
+    use alloc::format;
+    use alloc::string::ToString;
+
+    #[test]
+    fn test_split_text() {
+        let max_chars = (CONTENT_AREA_WIDTH / CHAR_WIDTH) as usize;
+        let word = "a".repeat(max_chars - 2);
+        let lines = split_text(format!("{} bb cc", word), CHAR_WIDTH);
+        assert_eq!([word.clone(), "bb cc".to_string()].to_vec(), lines);
+
+        // 空白のない長い単語は幅の位置で区切る
+        let long = "b".repeat(max_chars + 3);
+        assert_eq!(
+            ["b".repeat(max_chars), "bbb".to_string()].to_vec(),
+            split_text(long, CHAR_WIDTH)
+        );
+        assert_eq!(
+            ["".to_string()].to_vec(),
+            split_text("".to_string(), CHAR_WIDTH)
+        );
+    }
+
+    #[test]
+    fn test_line_fragments() {
+        let text = "word ".repeat(40);
+        let html = format!("<html><body><p>{}</p></body></html>", text);
+        let window = HtmlParser::new(HtmlTokenizer::new(html)).construct_tree();
+        let dom = window.borrow().document();
+        let layout_view = LayoutView::new(dom, &StyleSheet::new());
+
+        let text = layout_view
+            .root()
+            .expect("root should exist")
+            .borrow()
+            .first_child()
+            .expect("p should exist")
+            .borrow()
+            .first_child()
+            .expect("text should exist");
+        let fragments = text.borrow().line_fragments();
+        assert!(fragments.len() >= 2);
+        for pair in fragments.windows(2) {
+            assert!(pair[0].point.y() < pair[1].point.y());
+        }
+        for fragment in &fragments {
+            assert!(fragment.text.chars().count() as i64 * 8 <= CONTENT_AREA_WIDTH);
+        }
+        assert_eq!(
+            CHAR_WITH_PADDING * fragments.len() as i64,
+            text.borrow().size().height()
+        );
+    }
+
+    #[test]
+    fn test_expand_tabs() {
+        assert_eq!("        a", expand_tabs("\ta", 8));
+        assert_eq!("abc     d", expand_tabs("abc\td", 8));
+        assert_eq!("ab  c", expand_tabs("ab\tc", 4));
+        assert_eq!("no tab", expand_tabs("no tab", 8));
+    }
+
+    #[test]
+    fn test_preformatted_lines() {
+        let lines = preformatted_lines("\nx\ty\n\tz", 8);
+        assert_eq!(
+            vec!["x       y".to_string(), "        z".to_string()],
+            lines
+        );
+    }
+
+    #[test]
+    fn test_simple_selectors() {
+        let html = "<html><body><p>a</p><p id=\"x\">b</p><p class=\"c d\">c</p></body></html>";
+        let window = HtmlParser::new(HtmlTokenizer::new(html.to_string())).construct_tree();
+        let document = window.borrow().document();
+        let body =
+            get_target_element_node(Some(document), ElementKind::Body).expect("body should exist");
+
+        let mut nodes = Vec::new();
+        let mut child = body.borrow().first_child();
+        while let Some(node) = child {
+            child = node.borrow().next_sibling();
+            nodes.push(node);
+        }
+
+        let matched = |selector: Selector| -> Vec<bool> {
+            nodes
+                .iter()
+                .map(|n| is_selected(&n.borrow(), &selector))
+                .collect()
+        };
+        assert_eq!(
+            [true, true, true].to_vec(),
+            matched(Selector::TypeSelector("p".to_string()))
+        );
+        assert_eq!(
+            [false, true, false].to_vec(),
+            matched(Selector::IdSelector("x".to_string()))
+        );
+        assert_eq!(
+            [false, false, true].to_vec(),
+            matched(Selector::ClassSelector("c".to_string()))
+        );
+        assert_eq!(
+            [true, true, true].to_vec(),
+            matched(Selector::UniversalSelector)
+        );
+
+        // テキストノードにはどのセレクタもマッチしない
+        let text = nodes[0].borrow().first_child().expect("text should exist");
+        assert!(!is_selected(&text.borrow(), &Selector::UniversalSelector));
+    }
+
+    #[test]
+    fn test_not_selector() {
+        let html = "<html><body><p>a</p><p class=\"y\">b</p><p class=\"x\">c</p></body></html>";
+        let window = HtmlParser::new(HtmlTokenizer::new(html.to_string())).construct_tree();
+        let document = window.borrow().document();
+        let body =
+            get_target_element_node(Some(document), ElementKind::Body).expect("body should exist");
+
+        let selector = Selector::CompoundSelector(
+            [
+                Selector::TypeSelector("p".to_string()),
+                Selector::NotSelector(Box::new(Selector::ClassSelector("x".to_string()))),
+            ]
+            .to_vec(),
+        );
+
+        let mut matched = Vec::new();
+        let mut child = body.borrow().first_child();
+        while let Some(node) = child {
+            matched.push(is_selected(&node.borrow(), &selector));
+            child = node.borrow().next_sibling();
+        }
+        assert_eq!([true, true, false].to_vec(), matched);
+    }
+}
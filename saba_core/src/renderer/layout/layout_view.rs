@@ -1,13 +1,16 @@
 use crate::constants::CONTENT_AREA_WIDTH;
 use crate::display_item::DisplayItem;
-use crate::renderer::css::cssom::StyleSheet;
+use crate::renderer::css::cssom::{
+    cascade_tier, specificity, ComponentValue, Declaration, StyleSheet,
+};
 use crate::renderer::dom::api::get_target_element_node;
-use crate::renderer::dom::node::{ElementKind, Node};
+use crate::renderer::dom::node::{ElementKind, Node, NodeKind};
 use crate::renderer::layout::computed_style::DisplayType;
 use crate::renderer::layout::layout_object::{
-    LayoutObject, LayoutObjectKind, LayoutPoint, LayoutSize,
+    is_selected, LayoutObject, LayoutObjectKind, LayoutPoint, LayoutSize,
 };
 use alloc::rc::Rc;
+use alloc::string::{String, ToString};
 use alloc::vec::Vec;
 use core::cell::RefCell;
 
@@ -66,7 +69,7 @@ impl LayoutView {
     ) {
         if let Some(n) = node {
             n.borrow_mut().compute_position(
-                parent_point,
+                parent_point.clone(),
                 previous_sibling_kind,
                 previous_sibling_point,
                 previous_sibling_size,
@@ -77,6 +80,15 @@ impl LayoutView {
                 &first_child,
                 n.borrow().point(),
                 LayoutObjectKind::Block,
+                None,
+                None,
+            );
+
+            let next_sibling = n.borrow().next_sibling();
+            Self::calculate_node_position(
+                &next_sibling,
+                parent_point,
+                n.borrow().kind(),
                 Some(n.borrow().point()),
                 Some(n.borrow().size()),
             );
@@ -179,6 +191,15 @@ fn build_layout_tree(
             None => panic!("layout object should exist here"),
         };
 
+        // 画像はまだデコードできないので、代わりに代替テキストを表示する
+        if first_child.is_none() {
+            if let Some(text) = image_replacement_text(&n) {
+                let text_node = Rc::new(RefCell::new(Node::new(NodeKind::Text(text))));
+                text_node.borrow_mut().set_parent(Rc::downgrade(&n));
+                first_child = create_layout_object(&Some(text_node), &layout_object, cssom);
+            }
+        }
+
         obj.borrow_mut().set_first_child(first_child);
         obj.borrow_mut().set_next_sibling(next_sibling);
     }
@@ -186,6 +207,21 @@ fn build_layout_tree(
     layout_object
 }
 
+// img 要素の代わりに表示するテキスト。alt 属性がなければ "[image]" とする
+// alt 属性が空の場合は装飾用の画像とみなして何も表示しない
+fn image_replacement_text(node: &Rc<RefCell<Node>>) -> Option<String> {
+    let element = node.borrow().get_element()?;
+    if element.kind() != ElementKind::Img {
+        return None;
+    }
+
+    match element.get_attribute("alt") {
+        Some(alt) if alt.is_empty() => None,
+        Some(alt) => Some(alt),
+        None => Some("[image]".to_string()),
+    }
+}
+
 pub fn create_layout_object(
     node: &Option<Rc<RefCell<Node>>>,
     parent_obj: &Option<Rc<RefCell<LayoutObject>>>,
@@ -194,12 +230,28 @@ pub fn create_layout_object(
     if let Some(n) = node {
         let layout_object = Rc::new(RefCell::new(LayoutObject::new(n.clone(), parent_obj)));
 
-        for rule in &cssom.rules {
-            if layout_object.borrow().is_node_selected(&rule.selector) {
-                layout_object
-                    .borrow_mut()
-                    .cascading_style(rule.declarations.clone());
-            }
+        // 後に適用した宣言が優先されるので、(出所と !important の優先順位, 詳細度) の低い順に適用する
+        // どちらも同じルールは、スタイルシートに現れた順に適用する（安定ソート）
+        let mut matched_rules: Vec<_> = cssom
+            .rules
+            .iter()
+            .filter(|rule| layout_object.borrow().is_node_selected(&rule.selector))
+            .flat_map(|rule| [(rule, false), (rule, true)])
+            .collect();
+        matched_rules.sort_by_key(|(rule, important)| {
+            (
+                cascade_tier(rule.origin, *important),
+                specificity(&rule.selector),
+            )
+        });
+
+        for (rule, important) in matched_rules {
+            let declarations: Vec<Declaration> =
+                resolve_var_references(&rule.declarations, n, cssom)
+                    .into_iter()
+                    .filter(|d| d.important == important)
+                    .collect();
+            layout_object.borrow_mut().cascading_style(declarations);
         }
 
         let parent_style = if let Some(parent) = parent_obj {
@@ -220,16 +272,83 @@ pub fn create_layout_object(
     None
 }
 
+// var() で参照されている値をカスタムプロパティの値に置き換える
+// 解決できず、フォールバックもない宣言は無効として取り除く
+fn resolve_var_references(
+    declarations: &[Declaration],
+    node: &Rc<RefCell<Node>>,
+    cssom: &StyleSheet,
+) -> Vec<Declaration> {
+    let mut resolved = Vec::new();
+
+    for declaration in declarations {
+        let var_reference = match &declaration.var_reference {
+            Some(v) => v,
+            None => {
+                resolved.push(declaration.clone());
+                continue;
+            }
+        };
+
+        let value = match find_custom_property(&var_reference.name, node, cssom) {
+            Some(value) => value,
+            None => match &var_reference.fallback {
+                Some(fallback) => fallback.clone(),
+                None => continue,
+            },
+        };
+
+        let mut d = declaration.clone();
+        d.set_value(value);
+        resolved.push(d);
+    }
+
+    resolved
+}
+
+// カスタムプロパティは継承されるので、ノード自身から祖先に向かって宣言を探す
+fn find_custom_property(
+    name: &str,
+    node: &Rc<RefCell<Node>>,
+    cssom: &StyleSheet,
+) -> Option<ComponentValue> {
+    let mut value = None;
+
+    for rule in &cssom.rules {
+        if !is_selected(&node.borrow(), &rule.selector) {
+            continue;
+        }
+        for declaration in &rule.declarations {
+            if declaration.is_custom_property() && declaration.property == name {
+                value = Some(declaration.value.clone());
+            }
+        }
+    }
+
+    if value.is_some() {
+        return value;
+    }
+
+    let parent = node.borrow().parent().upgrade();
+    match parent {
+        Some(p) => find_custom_property(name, &p, cssom),
+        None => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::alloc::string::ToString;
-    use crate::renderer::css::cssom::CssParser;
+    use crate::constants::{CHAR_HEIGHT, CHAR_WIDTH, CHAR_WITH_PADDING};
+    use crate::renderer::css::cssom::{CssParser, Origin};
     use crate::renderer::css::token::CssTokenizer;
     use crate::renderer::dom::api::get_style_content;
     use crate::renderer::dom::node::{Element, NodeKind};
     use crate::renderer::html::parser::HtmlParser;
     use crate::renderer::html::token::HtmlTokenizer;
+    use crate::renderer::layout::computed_style::{Color, FontFamily};
+    use alloc::format;
     use alloc::string::String;
     use alloc::vec::Vec;
 
@@ -307,6 +426,67 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_block_geometry() {
+        let html = "<html><head></head><body><p>hi</p><p>yo</p></body></html>".to_string();
+        let layout_view = create_layout_view(html);
+
+        let first = layout_view
+            .root()
+            .expect("root should exist")
+            .borrow()
+            .first_child()
+            .expect("first p should exist");
+        let second = first
+            .borrow()
+            .next_sibling()
+            .expect("second p should exist");
+
+        // ブロック要素は親の幅いっぱいに広がり、縦に積み重なる
+        assert_eq!(CONTENT_AREA_WIDTH, first.borrow().size().width());
+        assert_eq!(
+            first.borrow().point().y() + first.borrow().size().height(),
+            second.borrow().point().y()
+        );
+        assert!(second.borrow().point().y() > first.borrow().point().y());
+    }
+
+    #[test]
+    fn test_text_wraps_at_content_width() {
+        let long_text = "a".repeat((CONTENT_AREA_WIDTH / CHAR_WIDTH) as usize + 1);
+        let html = format!(
+            "<html><head></head><body><p>{}</p></body></html>",
+            long_text
+        );
+        let layout_view = create_layout_view(html);
+
+        let text = layout_view
+            .root()
+            .expect("root should exist")
+            .borrow()
+            .first_child()
+            .expect("p should exist")
+            .borrow()
+            .first_child()
+            .expect("text should exist");
+        assert_eq!(CONTENT_AREA_WIDTH, text.borrow().size().width());
+        assert_eq!(2 * CHAR_WITH_PADDING, text.borrow().size().height());
+
+        // 複数バイトの文字も1文字として幅を数える
+        let html = "<html><head></head><body><p>ああ</p></body></html>".to_string();
+        let layout_view = create_layout_view(html);
+        let text = layout_view
+            .root()
+            .expect("root should exist")
+            .borrow()
+            .first_child()
+            .expect("p should exist")
+            .borrow()
+            .first_child()
+            .expect("text should exist");
+        assert_eq!(2 * CHAR_WIDTH, text.borrow().size().width());
+    }
+
     #[test]
     fn test_display_none() {
         let html = "<html><head><style>body{display:none;}</style></head><body>text</body></html>"
@@ -374,4 +554,280 @@ mod tests {
             .next_sibling()
             .is_none());
     }
+
+    #[test]
+    fn test_display_none_sibling_layout() {
+        let html = r#"<html><head><style>
+            .hidden { display: none; }
+            </style></head><body><p class="hidden"><span>gone</span></p><p>shown</p></body></html>"#
+            .to_string();
+        let layout_view = create_layout_view(html);
+
+        // 非表示の p とその子孫はレイアウトオブジェクトを作らず、場所も取らない
+        let p = layout_view
+            .root()
+            .expect("root should exist")
+            .borrow()
+            .first_child()
+            .expect("p should exist");
+        assert!(p.borrow().next_sibling().is_none());
+        assert_eq!(0, p.borrow().point().y());
+        assert_eq!(CHAR_WITH_PADDING, p.borrow().size().height());
+        let text = p.borrow().first_child().expect("text should exist");
+        assert_eq!(
+            NodeKind::Text("shown".to_string()),
+            text.borrow().node_kind()
+        );
+    }
+
+    #[test]
+    fn test_font_size_px() {
+        let html = r#"<html><head><style>
+            h1 { font-size: 32px }
+            </style></head><body><h1>title</h1><p>text</p></body></html>"#
+            .to_string();
+        let layout_view = create_layout_view(html);
+
+        let h1 = layout_view
+            .root()
+            .expect("root should exist")
+            .borrow()
+            .first_child()
+            .expect("h1 should exist");
+        let p = h1.borrow().next_sibling().expect("p should exist");
+        assert_eq!(
+            32,
+            h1.borrow()
+                .first_child()
+                .expect("text")
+                .borrow()
+                .font_size()
+        );
+        assert_eq!(
+            CHAR_HEIGHT,
+            p.borrow().first_child().expect("text").borrow().font_size()
+        );
+        assert!(h1.borrow().size().height() > p.borrow().size().height());
+        assert_eq!(h1.borrow().size().height(), p.borrow().point().y());
+    }
+
+    #[test]
+    fn test_code_font_family() {
+        let html = "<html><head></head><body><code>abc</code></body></html>".to_string();
+        let layout_view = create_layout_view(html);
+
+        let code = layout_view
+            .root()
+            .expect("root should exist")
+            .borrow()
+            .first_child()
+            .expect("code should exist");
+        assert_eq!(
+            NodeKind::Element(Element::new("code", Vec::new())),
+            code.borrow().node_kind()
+        );
+        assert_eq!(FontFamily::Monospace, code.borrow().style().font_family());
+
+        let text = code.borrow().first_child().expect("text node should exist");
+        assert_eq!(FontFamily::Monospace, text.borrow().style().font_family());
+    }
+
+    #[test]
+    fn test_custom_property() {
+        let html = r#"<html><head><style>
+            :root { --c: red }
+            p { color: var(--c) }
+            </style></head><body><p>text</p></body></html>"#
+            .to_string();
+        let layout_view = create_layout_view(html);
+
+        let p = layout_view
+            .root()
+            .expect("root should exist")
+            .borrow()
+            .first_child()
+            .expect("p should exist");
+        assert_eq!(
+            Color::from_name("red").expect("red should be valid"),
+            p.borrow().style().color()
+        );
+    }
+
+    #[test]
+    fn test_custom_property_fallback() {
+        let html = r#"<html><head><style>
+            p { color: var(--missing, blue) }
+            </style></head><body><p>text</p></body></html>"#
+            .to_string();
+        let layout_view = create_layout_view(html);
+
+        let p = layout_view
+            .root()
+            .expect("root should exist")
+            .borrow()
+            .first_child()
+            .expect("p should exist");
+        assert_eq!(
+            Color::from_name("blue").expect("blue should be valid"),
+            p.borrow().style().color()
+        );
+    }
+
+    #[test]
+    fn test_hex_color() {
+        let html = r#"<html><head><style>
+            p { color: #ff0000 }
+            </style></head><body><p>text</p></body></html>"#
+            .to_string();
+        let layout_view = create_layout_view(html);
+
+        let p = layout_view
+            .root()
+            .expect("root should exist")
+            .borrow()
+            .first_child()
+            .expect("p should exist");
+        assert_eq!(
+            Color::from_name("red").expect("red should be valid"),
+            p.borrow().style().color()
+        );
+    }
+
+    #[test]
+    fn test_important_wins() {
+        let html = r#"<html><head><style>
+            p { color: red !important }
+            p { color: blue }
+            </style></head><body><p>text</p></body></html>"#
+            .to_string();
+        let layout_view = create_layout_view(html);
+
+        let p = layout_view
+            .root()
+            .expect("root should exist")
+            .borrow()
+            .first_child()
+            .expect("p should exist");
+        assert_eq!(
+            Color::from_name("red").expect("red should be valid"),
+            p.borrow().style().color()
+        );
+    }
+
+    #[test]
+    fn test_specificity_wins() {
+        let html = r#"<html><head><style>
+            #x { color: blue }
+            p { color: red }
+            </style></head><body><p id="x">text</p><p>other</p></body></html>"#
+            .to_string();
+        let layout_view = create_layout_view(html);
+
+        let first = layout_view
+            .root()
+            .expect("root should exist")
+            .borrow()
+            .first_child()
+            .expect("first p should exist");
+        let second = first
+            .borrow()
+            .next_sibling()
+            .expect("second p should exist");
+        assert_eq!(
+            Color::from_name("blue").expect("blue should be valid"),
+            first.borrow().style().color()
+        );
+        assert_eq!(
+            Color::from_name("red").expect("red should be valid"),
+            second.borrow().style().color()
+        );
+    }
+
+    // user_css をユーザーのスタイルシートとして、ページのスタイルの後に加える
+    fn create_layout_view_with_user_style(html: &str, user_css: &str) -> LayoutView {
+        let t = HtmlTokenizer::new(html.to_string());
+        let window = HtmlParser::new(t).construct_tree();
+        let dom = window.borrow().document();
+        let style = get_style_content(dom.clone());
+        let mut cssom = CssParser::new(CssTokenizer::new(style)).parse_stylesheet();
+        let user_style = CssParser::new(CssTokenizer::new(user_css.to_string())).parse_stylesheet();
+        for mut rule in user_style.rules {
+            rule.set_origin(Origin::User);
+            cssom.rules.push(rule);
+        }
+        LayoutView::new(dom, &cssom)
+    }
+
+    fn first_color(layout_view: &LayoutView) -> Color {
+        layout_view
+            .root()
+            .expect("root should exist")
+            .borrow()
+            .first_child()
+            .expect("p should exist")
+            .borrow()
+            .style()
+            .color()
+    }
+
+    #[test]
+    fn test_user_style_beats_more_specific_author_style() {
+        let html = r#"<html><head><style>
+            #x { color: red }
+            .n { color: red }
+            </style></head><body><p id="x" class="n">text</p></body></html>"#;
+        let layout_view = create_layout_view_with_user_style(html, "p { color: blue; }");
+        assert_eq!(
+            Color::from_name("blue").expect("blue should be valid"),
+            first_color(&layout_view)
+        );
+    }
+
+    #[test]
+    fn test_cascade_tiers() {
+        // 作成者の !important は、詳細度に関係なくユーザーの通常の宣言に勝つ
+        let html = r#"<html><head><style>
+            p { color: red !important }
+            </style></head><body><p id="x">text</p></body></html>"#;
+        let layout_view = create_layout_view_with_user_style(html, "#x { color: blue; }");
+        assert_eq!(
+            Color::from_name("red").expect("red should be valid"),
+            first_color(&layout_view)
+        );
+
+        // ユーザーの !important は、より詳細な作成者の !important に勝つ
+        let html = r#"<html><head><style>
+            #x { color: red !important }
+            </style></head><body><p id="x">text</p></body></html>"#;
+        let layout_view = create_layout_view_with_user_style(html, "* { color: blue !important; }");
+        assert_eq!(
+            Color::from_name("blue").expect("blue should be valid"),
+            first_color(&layout_view)
+        );
+    }
+
+    fn painted_texts(html: &str) -> Vec<String> {
+        let layout_view = create_layout_view(html.to_string());
+        layout_view
+            .paint()
+            .into_iter()
+            .filter_map(|item| match item {
+                DisplayItem::Text { text, .. } => Some(text),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_image_alt_text() {
+        assert_eq!(
+            ["a".to_string(), "a cat".to_string()].to_vec(),
+            painted_texts("<html><head></head><body><p>a<img alt=\"a cat\"></p></body></html>")
+        );
+        assert_eq!(
+            ["[image]".to_string()].to_vec(),
+            painted_texts("<html><head></head><body><img src=\"cat.png\"></body></html>")
+        );
+        assert!(painted_texts("<html><head></head><body><img alt=\"\"></body></html>").is_empty());
+    }
 }
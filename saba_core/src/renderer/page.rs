@@ -1,24 +1,39 @@
+use crate::browser::LoadTimings;
 use crate::display_item::DisplayItem;
+use crate::error::Error;
 use crate::http::HttpResponse;
-use crate::renderer::css::cssom::{CssParser, StyleSheet};
+use crate::renderer::css::cssom::{CssParser, Origin, StyleSheet};
 use crate::renderer::css::token::CssTokenizer;
 use crate::renderer::dom::api::get_style_content;
-use crate::renderer::dom::node::Window;
+use crate::renderer::dom::node::{Node, Window};
 use crate::renderer::html::token::HtmlTokenizer;
+use crate::renderer::js::ast::JsParser;
+use crate::renderer::js::runtime::{JsRuntime, RuntimeValue};
+use crate::renderer::js::token::JsLexer;
+use crate::renderer::layout::computed_style::{Color, ComputedStyle};
+use crate::renderer::layout::layout_object::LayoutObject;
 use crate::renderer::layout::layout_view::LayoutView;
 use crate::{browser::Browser, renderer::html::parser::HtmlParser};
 use alloc::rc::{Rc, Weak};
-use alloc::string::String;
+use alloc::string::{String, ToString};
 use alloc::vec::Vec;
 use core::cell::RefCell;
 
+// ダークモードで既定の背景と文字に使う色
+static DARK_BACKGROUND_COLOR: &str = "#202020";
+static DARK_TEXT_COLOR: &str = "#e0e0e0";
+
 #[derive(Debug, Clone)]
 pub struct Page {
     browser: Weak<RefCell<Browser>>,
     frame: Option<Rc<RefCell<Window>>>,
     style: Option<StyleSheet>,
+    user_style: Option<StyleSheet>, // ユーザーが指定したスタイルシート
     layout_view: Option<LayoutView>,
     display_items: Vec<DisplayItem>,
+    xhtml: bool,           // 読み込んだ文書が XHTML かどうか
+    dark_mode: bool,       // 描画するときに色をダークモードの配色に置き換えるかどうか
+    js_runtime: JsRuntime, // コンソールから実行したスクリプトの変数を保持する
 }
 
 impl Page {
@@ -27,26 +42,63 @@ impl Page {
             browser: Weak::new(),
             frame: None,
             style: None,
+            user_style: None,
             layout_view: None,
             display_items: Vec::new(),
+            xhtml: false,
+            dark_mode: false,
+            js_runtime: JsRuntime::new(),
         }
     }
 
     pub fn receive_response(&mut self, response: HttpResponse) {
-        self.create_frame(response.body());
+        self.receive_response_with_clock(response, &|| 0);
+    }
+
+    // now で時刻を測りながらレスポンスを処理し、各段階の所要時間を返す
+    // 取得（fetch）の時間はここでは測らないので 0 のまま
+    pub fn receive_response_with_clock(
+        &mut self,
+        response: HttpResponse,
+        now: &dyn Fn() -> u64,
+    ) -> LoadTimings {
+        let mut timings = LoadTimings::default();
+
+        // 新しい文書では、前のページで定義した変数は見えない
+        self.js_runtime = JsRuntime::new();
+        self.create_frame(response.body(), now, &mut timings);
 
+        let cascade_start = now();
         self.set_layout_view();
+        timings.cascade = now().saturating_sub(cascade_start);
+
+        // ページ内のスクリプトはまだ実行しないので、script は 0 のまま
         self.paint_tree();
+
+        timings
     }
 
-    fn create_frame(&mut self, html: String) {
+    // 受け取り途中の HTML を解析して描画する。時間は測らない
+    pub fn receive_partial_body(&mut self, html: String) {
+        self.create_frame(html, &|| 0, &mut LoadTimings::default());
+        self.set_layout_view();
+        self.paint_tree();
+    }
+
+    fn create_frame(&mut self, html: String, now: &dyn Fn() -> u64, timings: &mut LoadTimings) {
+        let html_start = now();
         let html_tokenizer = HtmlTokenizer::new(html);
-        let frame = HtmlParser::new(html_tokenizer).construct_tree();
+        let mut parser = HtmlParser::new(html_tokenizer);
+        parser.set_xhtml(self.xhtml);
+        let frame = parser.construct_tree();
         let dom = frame.borrow().document();
+        timings.parse_html = now().saturating_sub(html_start);
 
+        let css_start = now();
         let style = get_style_content(dom);
         let css_tokenizer = CssTokenizer::new(style);
         let cssom = CssParser::new(css_tokenizer).parse_stylesheet();
+        timings.parse_css = now().saturating_sub(css_start);
 
         self.frame = Some(frame);
         self.style = Some(cssom);
@@ -58,25 +110,101 @@ impl Page {
             None => return,
         };
 
-        let style = match self.style.clone() {
+        let mut style = match self.style.clone() {
             Some(style) => style,
             None => return,
         };
 
+        // ユーザースタイルのルールには出所を記録し、カスケードで作成者スタイルより優先させる
+        if let Some(user_style) = &self.user_style {
+            style
+                .rules
+                .extend(user_style.rules.iter().cloned().map(|mut rule| {
+                    rule.set_origin(Origin::User);
+                    rule
+                }));
+        }
+
         let layout_view = LayoutView::new(dom, &style);
         self.layout_view = Some(layout_view);
     }
 
     fn paint_tree(&mut self) {
         if let Some(layout_view) = &self.layout_view {
-            self.display_items = layout_view.paint();
+            let mut display_items = layout_view.paint();
+            if self.dark_mode {
+                for item in &mut display_items {
+                    match item {
+                        DisplayItem::Rect { style, .. } | DisplayItem::Text { style, .. } => {
+                            Self::apply_dark_colors(style)
+                        }
+                    }
+                }
+            }
+            self.display_items = display_items;
         }
     }
 
+    // 既定の白い背景と黒い文字は暗い背景と明るい文字にし、作成者が指定したそれ以外の色は明暗を反転する
+    // 計算済みの値を書き換えると子要素が反転済みの色を継承してしまうので、描画の直前に置き換える
+    fn apply_dark_colors(style: &mut ComputedStyle) {
+        let background_color = style.background_color();
+        style.set_background_color(if background_color == Color::white() {
+            Color::from_hex(DARK_BACKGROUND_COLOR).expect("failed to create a color")
+        } else {
+            background_color.inverted()
+        });
+
+        let color = style.color();
+        style.set_color(if color == Color::black() {
+            Color::from_hex(DARK_TEXT_COLOR).expect("failed to create a color")
+        } else {
+            color.inverted()
+        });
+    }
+
+    // 次に受け取るレスポンスを XHTML として解析するかどうか
+    pub fn set_xhtml(&mut self, xhtml: bool) {
+        self.xhtml = xhtml;
+    }
+
+    // ダークモードを切り替え、すでにページを読み込んでいれば再描画する
+    // レイアウトは変わらないので、描画だけをやり直す
+    pub fn set_dark_mode(&mut self, dark_mode: bool) {
+        self.dark_mode = dark_mode;
+        self.paint_tree();
+    }
+
     pub fn set_browser(&mut self, browser: Weak<RefCell<Browser>>) {
         self.browser = browser;
     }
 
+    // ユーザースタイルを設定し、すでにページを読み込んでいれば再描画する
+    pub fn set_user_style(&mut self, style: StyleSheet) {
+        self.user_style = Some(style);
+
+        if self.frame.is_some() {
+            self.set_layout_view();
+            self.paint_tree();
+        }
+    }
+
+    // src をこのページの実行環境で評価し、最後の文の値を返す
+    // 前回までの呼び出しで定義した変数はそのまま使える
+    pub fn evaluate_script(&mut self, src: &str) -> Result<RuntimeValue, Error> {
+        let program = JsParser::new(JsLexer::new(src.to_string())).parse_ast();
+        self.js_runtime.evaluate_program(&program)
+    }
+
+    pub fn document(&self) -> Option<Rc<RefCell<Node>>> {
+        self.frame.as_ref().map(|frame| frame.borrow().document())
+    }
+
+    // レイアウトツリーのルート（body 要素のレイアウトオブジェクト）
+    pub fn layout_root(&self) -> Option<Rc<RefCell<LayoutObject>>> {
+        self.layout_view.as_ref().and_then(|view| view.root())
+    }
+
     pub fn display_items(&self) -> Vec<DisplayItem> {
         self.display_items.clone()
     }
@@ -107,6 +107,74 @@ impl Url {
     pub fn searchpart(&self) -> String {
         self.searchpart.clone()
     }
+
+    // キャッシュや比較のために正規化した URL を返す
+    // スキームとホストを小文字にし、既定のポートを省略して、パスの "." と ".." を取り除く
+    pub fn normalize(&self) -> Url {
+        let host = self.host.to_ascii_lowercase();
+        let path = normalize_path(&self.path);
+
+        let mut url = String::from("http://");
+        url.push_str(&host);
+        if self.port != "80" {
+            url.push(':');
+            url.push_str(&self.port);
+        }
+        if !path.is_empty() || !self.searchpart.is_empty() {
+            url.push('/');
+            url.push_str(&path);
+        }
+        if !self.searchpart.is_empty() {
+            url.push('?');
+            url.push_str(&self.searchpart);
+        }
+
+        Url {
+            url,
+            host,
+            port: self.port.clone(),
+            path,
+            searchpart: self.searchpart.clone(),
+        }
+    }
+
+    pub fn url(&self) -> String {
+        self.url.clone()
+    }
+
+    // パスの最後の部分の拡張子を小文字で返す。拡張子がなければ None
+    pub fn extension(&self) -> Option<String> {
+        let file_name = self.path.rsplit('/').next()?;
+        let (name, ext) = file_name.rsplit_once('.')?;
+        if name.is_empty() || ext.is_empty() {
+            return None;
+        }
+        Some(ext.to_ascii_lowercase())
+    }
+}
+
+// パスの "." を取り除き、".." で1つ上の階層に戻る。ルートより上には戻らない
+fn normalize_path(path: &str) -> String {
+    let mut segments: Vec<&str> = Vec::new();
+    let mut ends_with_dir = false;
+
+    for segment in path.split('/') {
+        ends_with_dir = false;
+        match segment {
+            "." => ends_with_dir = true,
+            ".." => {
+                segments.pop();
+                ends_with_dir = true;
+            }
+            _ => segments.push(segment),
+        }
+    }
+
+    // "a/b/.." のようにディレクトリで終わる場合は、末尾の '/' を残す
+    if ends_with_dir && !segments.is_empty() {
+        segments.push("");
+    }
+    segments.join("/")
 }
 
 #[cfg(test)]
@@ -191,4 +259,47 @@ mod tests {
         let expected = Err("Only HTTP scheme is supported.".to_string());
         assert_eq!(expected, Url::new(url).parse());
     }
+
+    #[test]
+    fn test_extension() {
+        let url = Url::new("http://example.com/dir/style.CSS?v=1".to_string())
+            .parse()
+            .expect("failed to parse url");
+        assert_eq!(Some("css".to_string()), url.extension());
+    }
+
+    #[test]
+    fn test_no_extension() {
+        for url in [
+            "http://example.com",
+            "http://example.com/dir/",
+            "http://example.com/.hidden",
+        ] {
+            let url = Url::new(url.to_string())
+                .parse()
+                .expect("failed to parse url");
+            assert_eq!(None, url.extension());
+        }
+    }
+
+    #[test]
+    fn test_normalize() {
+        let url = Url::new("http://Example.com:80/a/./b/../c".to_string())
+            .parse()
+            .expect("failed to parse url")
+            .normalize();
+        assert_eq!("http://example.com/a/c".to_string(), url.url());
+        assert_eq!("example.com".to_string(), url.host());
+        assert_eq!("a/c".to_string(), url.path());
+    }
+
+    #[test]
+    fn test_normalize_beyond_root() {
+        let url = Url::new("http://example.com:8080/../../x/y/..?q=1".to_string())
+            .parse()
+            .expect("failed to parse url")
+            .normalize();
+        assert_eq!("http://example.com:8080/x/?q=1".to_string(), url.url());
+        assert_eq!("x/".to_string(), url.path());
+    }
 }